@@ -1,18 +1,31 @@
+mod stream;
+
+pub use stream::{GrowingFile, StreamSource};
+
 use anyhow::{Context, Result, bail};
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+/// File formats the player can decode.
+pub const PLAYABLE_FORMATS: &[&str] = &["mp3", "flac", "ogg", "wav"];
+
+pub fn can_play(format: &str) -> bool {
+    PLAYABLE_FORMATS.contains(&format.to_lowercase().as_str())
+}
+
 pub struct AudioPlayer {
     _stream: OutputStream,
     _stream_handle: OutputStreamHandle,
     sink: Sink,
     volume: Arc<AtomicU8>,
     is_playing: Arc<AtomicBool>,
+    /// Position the current file counts as finished at, if it ends early
+    end: Mutex<Option<Duration>>,
 }
 
 impl AudioPlayer {
@@ -33,10 +46,12 @@ impl AudioPlayer {
             sink,
             volume,
             is_playing,
+            end: Mutex::new(None),
         })
     }
 
-    pub fn play_file(&self, path: &Path) -> Result<()> {
+    /// Play a file from `start`, counting it as finished at `end`.
+    pub fn play_file(&self, path: &Path, start: Duration, end: Option<Duration>) -> Result<()> {
         if !path.exists() {
             bail!("Audio file not found: {}", path.display());
         }
@@ -50,12 +65,26 @@ impl AudioPlayer {
 
         self.sink.clear();
         self.sink.append(source);
+        if !start.is_zero() {
+            let _ = self.sink.try_seek(start);
+        }
         self.sink.play();
         self.is_playing.store(true, Ordering::SeqCst);
+        *self.end.lock().unwrap() = end;
 
         Ok(())
     }
 
+    /// Play audio that is still being downloaded, decoded beforehand with
+    /// `StreamSource::decode` so this doesn't wait for the download.
+    pub fn play_stream(&self, source: StreamSource<Decoder<GrowingFile>>) {
+        self.sink.clear();
+        self.sink.append(source);
+        self.sink.play();
+        self.is_playing.store(true, Ordering::SeqCst);
+        *self.end.lock().unwrap() = None;
+    }
+
     pub fn pause(&self) {
         self.sink.pause();
         self.is_playing.store(false, Ordering::SeqCst);
@@ -107,8 +136,15 @@ impl AudioPlayer {
         self.sink.empty()
     }
 
+    /// Whether the file has played to its end, or past the end it was
+    /// given.
     pub fn is_finished(&self) -> bool {
-        self.sink.empty() && !self.sink.is_paused()
+        let past_end = self
+            .end
+            .lock()
+            .unwrap()
+            .is_some_and(|end| self.sink.get_pos() >= end);
+        past_end || (self.sink.empty() && !self.sink.is_paused())
     }
 
     #[allow(dead_code)]
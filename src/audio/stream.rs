@@ -0,0 +1,222 @@
+use rodio::Source;
+use rodio::source::SeekError;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A file that is still being written, e.g. by a streaming download.
+///
+/// Reads never wait: at the current end they return what has been written
+/// so far, possibly nothing. `prebuffer` waits for data before decoding
+/// starts, and `StreamSource` covers later gaps.
+pub struct GrowingFile {
+    file: File,
+    complete: Arc<AtomicBool>,
+    /// Set once the player no longer wants the stream
+    closed: Arc<AtomicBool>,
+}
+
+impl GrowingFile {
+    pub fn open(
+        path: &Path,
+        complete: Arc<AtomicBool>,
+        closed: Arc<AtomicBool>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            file: File::open(path)?,
+            complete,
+            closed,
+        })
+    }
+
+    /// Wait until `min_bytes` have been written or the writer is done.
+    /// Fails if that takes longer than `timeout`, or the stream is closed.
+    pub fn prebuffer(&self, min_bytes: u64, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if self.complete.load(Ordering::SeqCst) || self.file.metadata()?.len() >= min_bytes {
+                return Ok(());
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "Stream was closed",
+                ));
+            }
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Timed out waiting for the stream",
+                ));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Read for GrowingFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for GrowingFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+/// A decoded stream that plays silence, instead of ending, when playback
+/// catches up with the download.
+pub struct StreamSource<S> {
+    inner: S,
+    complete: Arc<AtomicBool>,
+    /// Samples of silence left before trying the decoder again
+    silence: usize,
+}
+
+impl<S> StreamSource<S> {
+    pub fn new(inner: S, complete: Arc<AtomicBool>) -> Self {
+        Self {
+            inner,
+            complete,
+            silence: 0,
+        }
+    }
+}
+
+impl StreamSource<rodio::Decoder<GrowingFile>> {
+    /// Decode `stream` once enough of it has arrived.
+    pub fn decode(stream: GrowingFile, min_bytes: u64, timeout: Duration) -> anyhow::Result<Self> {
+        stream.prebuffer(min_bytes, timeout)?;
+        let complete = Arc::clone(&stream.complete);
+        let decoder = rodio::Decoder::new(stream)?;
+        Ok(Self::new(decoder, complete))
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for StreamSource<S> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Some(0);
+        }
+
+        // Check before decoding so data written just before completion is
+        // still played
+        let complete = self.complete.load(Ordering::SeqCst);
+        match self.inner.next() {
+            Some(sample) => Some(sample),
+            None if complete => None,
+            None => {
+                // A tenth of a second, in whole frames
+                let frame = self.inner.channels().max(1) as usize;
+                let samples = (self.inner.sample_rate() as usize / 10).max(1) * frame;
+                self.silence = samples - 1;
+                Some(0)
+            }
+        }
+    }
+}
+
+impl<S: Source<Item = i16>> Source for StreamSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.silence {
+            0 => self.inner.current_frame_len(),
+            n => Some(n),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.silence = 0;
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+    use std::io::Write;
+
+    #[test]
+    fn test_growing_file_prebuffers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stream.mp3");
+        let mut writer = File::create(&path).unwrap();
+        let complete = Arc::new(AtomicBool::new(false));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let mut reader =
+            GrowingFile::open(&path, Arc::clone(&complete), Arc::clone(&closed)).unwrap();
+
+        // Reads return what's there without waiting
+        let mut buf = [0u8; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        let producer = thread::spawn(move || {
+            for chunk in [b"abc", b"def"] {
+                thread::sleep(Duration::from_millis(100));
+                writer.write_all(chunk).unwrap();
+            }
+            complete.store(true, Ordering::SeqCst);
+        });
+
+        reader.prebuffer(6, Duration::from_secs(5)).unwrap();
+        producer.join().unwrap();
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "abcdef");
+
+        // Stalled and closed streams give up
+        let path = dir.path().join("stalled.mp3");
+        File::create(&path).unwrap();
+        let stalled = GrowingFile::open(&path, Arc::default(), Arc::default()).unwrap();
+        let error = stalled
+            .prebuffer(1, Duration::from_millis(100))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        closed.store(true, Ordering::SeqCst);
+        let closed = GrowingFile::open(&path, Arc::default(), closed).unwrap();
+        let error = closed.prebuffer(1, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_stream_source_fills_gaps() {
+        let complete = Arc::new(AtomicBool::new(false));
+        let inner = SamplesBuffer::new(2, 20, vec![1i16, 2]);
+        let mut source = StreamSource::new(inner, Arc::clone(&complete));
+
+        assert_eq!(source.next(), Some(1));
+        assert_eq!(source.next(), Some(2));
+        // Out of data mid-download: silence in whole frames
+        assert_eq!(source.next(), Some(0));
+        assert_eq!(source.current_frame_len(), Some(3));
+        let gap: Vec<i16> = (0..3).map_while(|_| source.next()).collect();
+        assert_eq!(gap, vec![0; 3]);
+
+        complete.store(true, Ordering::SeqCst);
+        assert_eq!(source.next(), None);
+    }
+}
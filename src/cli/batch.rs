@@ -0,0 +1,187 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+
+use crate::download::DownloadPhase;
+
+/// One line of a batch file: `<url> [alias] [playlist]`.
+///
+/// Use `-` as the alias to set only a playlist. The playlist is the rest of
+/// the line, so it may contain spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub url: String,
+    pub alias: Option<String>,
+    pub playlist: Option<String>,
+}
+
+/// Read batch entries from a file, or from stdin when `source` is `-`.
+pub fn read_batch(source: &str) -> Result<Vec<BatchEntry>> {
+    let content = if source == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .with_context(|| "Failed to read URLs from stdin")?;
+        content
+    } else {
+        fs::read_to_string(source).with_context(|| format!("Failed to read file: {source}"))?
+    };
+
+    parse_batch(&content)
+}
+
+pub fn parse_batch(content: &str) -> Result<Vec<BatchEntry>> {
+    let mut entries = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (url, rest) = split_field(line);
+        if !url.contains("://") {
+            bail!("Line {}: expected a URL, found '{url}'", i + 1);
+        }
+
+        let (alias, playlist) = split_field(rest);
+        let alias = Some(alias).filter(|a| !a.is_empty() && *a != "-");
+        let playlist = Some(playlist).filter(|p| !p.is_empty());
+
+        entries.push(BatchEntry {
+            url: url.to_string(),
+            alias: alias.map(|a| a.to_string()),
+            playlist: playlist.map(|p| p.to_string()),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Drop entries whose URL appeared earlier in the batch, returning how many
+/// were dropped.
+pub fn dedupe_batch(entries: &mut Vec<BatchEntry>) -> usize {
+    let before = entries.len();
+    let mut seen = HashSet::new();
+    entries.retain(|e| seen.insert(e.url.clone()));
+    before - entries.len()
+}
+
+fn split_field(s: &str) -> (&str, &str) {
+    match s.split_once(char::is_whitespace) {
+        Some((field, rest)) => (field, rest.trim()),
+        None => (s, ""),
+    }
+}
+
+/// Multi-line progress display for concurrent downloads.
+///
+/// Finished items are printed once and scroll up; in-flight items are redrawn
+/// in place below them. Without a terminal only finished items are printed.
+pub struct BatchProgress {
+    total: usize,
+    interactive: bool,
+    /// In-flight items by index, as (title, status)
+    active: BTreeMap<usize, (String, String)>,
+    drawn: usize,
+}
+
+impl BatchProgress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            interactive: io::stderr().is_terminal(),
+            active: BTreeMap::new(),
+            drawn: 0,
+        }
+    }
+
+    pub fn start(&mut self, index: usize, title: &str) {
+        self.active
+            .insert(index, (title.to_string(), "Starting...".to_string()));
+        self.redraw(None);
+    }
+
+    pub fn progress(&mut self, index: usize, phase: DownloadPhase) {
+        let Some((_, status)) = self.active.get_mut(&index) else {
+            return;
+        };
+
+        *status = phase.to_string();
+        self.redraw(None);
+    }
+
+    pub fn finish(&mut self, index: usize, message: &str) {
+        self.active.remove(&index);
+        let line = format!("{} {message}", self.counter(index));
+        self.redraw(Some(&line));
+    }
+
+    fn counter(&self, index: usize) -> String {
+        format!("[{}/{}]", index + 1, self.total)
+    }
+
+    fn redraw(&mut self, finished: Option<&str>) {
+        let mut stderr = io::stderr().lock();
+
+        if !self.interactive {
+            if let Some(line) = finished {
+                let _ = writeln!(stderr, "{line}");
+            }
+            return;
+        }
+
+        for _ in 0..self.drawn {
+            let _ = write!(stderr, "\x1b[1A\x1b[2K");
+        }
+        if let Some(line) = finished {
+            let _ = writeln!(stderr, "{line}");
+        }
+        for (index, (title, status)) in &self.active {
+            let title: String = title.chars().take(60).collect();
+            let _ = writeln!(stderr, "{} {status:<28}  {title}", self.counter(*index));
+        }
+        self.drawn = self.active.len();
+        let _ = stderr.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch() {
+        let content = "\
+# Focus music
+https://youtu.be/aaa
+https://youtu.be/bbb  focus1
+https://youtu.be/ccc - Deep Work
+
+https://youtu.be/ddd deep Deep Work
+";
+        let entries = parse_batch(content).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].alias, None);
+        assert_eq!(entries[0].playlist, None);
+        assert_eq!(entries[1].alias.as_deref(), Some("focus1"));
+        assert_eq!(entries[2].alias, None);
+        assert_eq!(entries[2].playlist.as_deref(), Some("Deep Work"));
+        assert_eq!(entries[3].alias.as_deref(), Some("deep"));
+        assert_eq!(entries[3].playlist.as_deref(), Some("Deep Work"));
+
+        assert!(parse_batch("not-a-url alias").is_err());
+    }
+
+    #[test]
+    fn test_dedupe_batch() {
+        let mut entries =
+            parse_batch("https://youtu.be/aaa a\nhttps://youtu.be/bbb\nhttps://youtu.be/aaa b\n")
+                .unwrap();
+        assert_eq!(dedupe_batch(&mut entries), 1);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].alias.as_deref(), Some("a"));
+        assert_eq!(entries[1].url, "https://youtu.be/bbb");
+    }
+}
@@ -1,15 +1,38 @@
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use uuid::Uuid;
+
+use super::batch::{BatchEntry, BatchProgress, dedupe_batch, read_batch};
+use super::edit::{TrackEdit, edit_in_editor};
+use crate::audio;
 use crate::config::Config;
+use crate::convert::{Target, convert_file};
 use crate::daemon::Daemon;
 use crate::db::Database;
-use crate::download::{DownloadPhase, Downloader};
+use crate::download::{
+    DownloadError, DownloadPhase, Downloader, START_BOOKMARK, audio_file_key, audio_file_stem,
+    extract_start_time, extract_video_id, is_collection_url, resolve_source,
+};
 use crate::ipc::{DaemonClient, DaemonResponse};
-use crate::models::{LibraryExport, PlaybackState, Track};
+use crate::models::{
+    FileStatus, LibraryExport, LoopRegion, PlaybackState, SourceStatus, Subscription, Track,
+};
+use crate::storage;
+use crate::subscription::{SyncEvent, SyncFilter, SyncReport, sync_subscription};
+
+/// Consecutive network failures after which `mixyt check` assumes the
+/// machine is offline and stops.
+const NETWORK_ERRORS_OFFLINE: usize = 5;
 
 pub struct App {
     pub config: Config,
@@ -23,13 +46,131 @@ impl App {
 
         let db = Database::open(&config.db_path()).with_context(|| "Failed to open database")?;
 
-        Ok(Self { config, db })
+        let app = Self { config, db };
+        app.migrate_relative_paths()?;
+        app.migrate_file_names()?;
+
+        Ok(app)
+    }
+
+    /// Store absolute paths into the audio directory as relative ones, so the
+    /// library survives being moved. Paths into a previous library location
+    /// are recovered when the file is now in the audio directory.
+    fn migrate_relative_paths(&self) -> Result<()> {
+        let audio_dir = self.config.audio_dir();
+
+        for track in self.db.get_all_tracks()? {
+            let path = Path::new(&track.file_path);
+            if !path.is_absolute() {
+                continue;
+            }
+
+            let relative = match path.strip_prefix(&audio_dir) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) if !path.exists() => match path.file_name() {
+                    Some(name) if audio_dir.join(name).exists() => name.into(),
+                    _ => continue,
+                },
+                Err(_) => continue,
+            };
+
+            self.db
+                .update_track_file_path(&track.id, &relative.to_string_lossy())?;
+        }
+
+        Ok(())
+    }
+
+    /// Rename audio files from the old title-only scheme to `<title> [<key>]`
+    /// (see `audio_file_stem`), once per library. A file that can't be
+    /// renamed keeps its old name and still plays.
+    fn migrate_file_names(&self) -> Result<()> {
+        const TASK: &str = "name-files-by-key";
+        if self.db.is_task_done(TASK)? {
+            return Ok(());
+        }
+
+        // yt-dlp's media ID for YouTube is the video ID; other sources
+        // fall back to the track's own ID
+        let key_for = |track: &Track| {
+            let video_id = extract_video_id(&track.url);
+            audio_file_key(video_id.as_deref(), track)
+        };
+        let mut by_file: HashMap<&str, Vec<&Track>> = HashMap::new();
+
+        let tracks = self.db.get_all_tracks()?;
+        for track in tracks.iter().filter(|t| !t.file_path.is_empty()) {
+            // Old names can't contain brackets, so any bracketed stem is
+            // already keyed, whichever source ID it uses
+            let migrated = Path::new(&track.file_path)
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().ends_with(']'));
+            if !migrated {
+                by_file.entry(&track.file_path).or_default().push(track);
+            }
+        }
+
+        for (file_path, mut tracks) in by_file {
+            // When titles collided, the newest download overwrote the file,
+            // so it belongs to the newest track
+            tracks.sort_by_key(|t| std::cmp::Reverse(t.added_at));
+            let stored = Path::new(file_path);
+            let old_path = self.config.resolve_audio_path(file_path);
+
+            for (i, track) in tracks.iter().enumerate() {
+                let mut new_stored =
+                    stored.with_file_name(audio_file_stem(&track.title, &key_for(track)));
+                if let Some(ext) = stored.extension() {
+                    new_stored.set_extension(ext);
+                }
+                let new_path = self.config.audio_dir().join(&new_stored);
+
+                if i > 0 {
+                    eprintln!(
+                        "Warning: '{}' was overwritten by a track with the same title; remove and re-add it",
+                        track.display_name()
+                    );
+                    self.db
+                        .update_track_file_status(&track.id, FileStatus::Missing, Utc::now())?;
+                } else if old_path.exists()
+                    && let Err(e) = fs::rename(&old_path, &new_path)
+                {
+                    eprintln!(
+                        "Warning: failed to rename {} to {}: {e}",
+                        old_path.display(),
+                        new_path.display()
+                    );
+                    continue;
+                }
+
+                self.db
+                    .update_track_file_path(&track.id, &new_stored.to_string_lossy())?;
+            }
+        }
+
+        self.db.mark_task_done(TASK)
     }
 
     fn client(&self) -> DaemonClient {
         DaemonClient::new(self.config.socket_path())
     }
 
+    /// Evict least-recently-played tracks if the library is over
+    /// `storage.max_size`. Tracks in `keep` are never evicted.
+    fn enforce_quota(&self, keep: &[Uuid]) -> Result<()> {
+        let evicted = storage::enforce_quota(&self.config, &self.db, keep)?;
+        if !evicted.is_empty() {
+            println!(
+                "Evicted {} tracks to stay under storage.max_size (downloaded again when played):",
+                evicted.len()
+            );
+            for track in &evicted {
+                println!("  {}", track.display_name());
+            }
+        }
+        Ok(())
+    }
+
     fn ensure_daemon(&self) -> Result<DaemonClient> {
         let client = self.client();
         if !client.is_daemon_running() {
@@ -44,82 +185,95 @@ impl App {
     }
 
     fn find_track(&self, query: &str) -> Result<Track> {
+        if let Some(track) = self.db.get_track_by_alias(query)? {
+            return Ok(track);
+        }
+
         let tracks = self.db.get_all_tracks()?;
 
         if tracks.is_empty() {
             bail!("Library is empty. Add tracks with: mixyt add <url>");
         }
 
-        // Try exact match first
-        for track in &tracks {
-            if track.alias.as_deref() == Some(query)
-                || track.title.to_lowercase() == query.to_lowercase()
-            {
-                return Ok(track.clone());
-            }
+        match match_track(&tracks, query) {
+            Some(track) => Ok(track.clone()),
+            None => bail!("No track found matching '{query}'"),
         }
+    }
 
-        // Fuzzy search
-        let matcher = SkimMatcherV2::default();
-        let mut matches: Vec<_> = tracks
-            .iter()
-            .filter_map(|track| {
-                let title_score = matcher.fuzzy_match(&track.title, query).unwrap_or(0);
-                let alias_score = track
-                    .alias
-                    .as_ref()
-                    .and_then(|a| matcher.fuzzy_match(a, query))
-                    .unwrap_or(0);
-                let score = title_score.max(alias_score);
-                if score > 0 {
-                    Some((track, score))
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        matches.sort_by(|a, b| b.1.cmp(&a.1));
+    fn check_alias_free(&self, alias: &str) -> Result<()> {
+        if let Some(other) = self.db.get_track_by_alias(alias)? {
+            bail!(
+                "Alias '{alias}' is already used by '{}'",
+                other.display_title()
+            );
+        }
+        Ok(())
+    }
 
-        if let Some((track, _)) = matches.first() {
-            Ok((*track).clone())
-        } else {
-            bail!("No track found matching '{query}'");
+    /// Override the configured audio format and quality for this run.
+    pub fn set_audio_format(
+        &mut self,
+        format: Option<String>,
+        quality: Option<String>,
+        keep_original: bool,
+    ) -> Result<()> {
+        let audio = &mut self.config.audio;
+
+        if keep_original {
+            audio.format = "original".to_string();
+        } else if let Some(format) = format {
+            audio.format = format.to_lowercase();
+        }
+        if let Some(quality) = quality {
+            audio.quality = quality;
         }
+
+        // Fail before downloading anything
+        audio.yt_dlp_quality()?;
+        Ok(())
     }
 
     // Command implementations
 
-    pub fn add(&self, url: &str, alias: Option<&str>) -> Result<()> {
+    pub fn add(&self, url: &str, alias: Option<&str>, save_playlist: bool) -> Result<()> {
+        let downloader = Downloader::new(self.config.clone());
+
         println!("Checking dependencies...");
-        Downloader::check_dependencies()?;
+        downloader.check_dependencies([url])?;
 
-        let downloader = Downloader::new(self.config.clone());
+        if is_collection_url(url) {
+            if alias.is_some() {
+                bail!("--alias can only be used when adding a single video");
+            }
+            return self.add_collection(&downloader, url, save_playlist);
+        }
+        if let Some(a) = alias {
+            self.check_alias_free(a)?;
+        }
 
-        // Get canonical URL to check for duplicates
-        println!("Checking video info...");
-        let (title, canonical_url, _duration) = downloader.get_video_info(url)?;
+        // A YouTube URL's video ID is enough to spot a duplicate; otherwise
+        // the canonical URL is needed
+        let mut existing = self.db.get_track_by_source(url)?;
+        if existing.is_none() {
+            println!("Checking video info...");
+            let (_, canonical_url, _) = downloader.get_video_info(url).map_err(explain)?;
+            existing = self.db.get_track_by_source(&canonical_url)?;
+        }
 
-        // Check if already in library by canonical URL
-        if let Some(existing) = self.db.get_track_by_url(&canonical_url)? {
+        if let Some(existing) = existing {
             println!("Track already in library: {}", existing.display_name());
             println!(
                 "Use 'mixyt remove \"{}\"' first if you want to re-add it.",
-                title
+                existing.title
             );
             return Ok(());
         }
 
         eprintln!("Downloading audio...");
-        let mut track = downloader.download(url, |phase| match phase {
-            DownloadPhase::Downloading { percent, speed, eta } => {
-                eprint!("\r  [{:5.1}%] {} ETA {}    ", percent, speed, eta);
-            }
-            DownloadPhase::Converting => {
-                eprint!("\r  Converting audio...          \n");
-            }
-        })?;
+        let result = downloader.download(url, extract_start_time(url), print_download_progress);
         eprintln!();
+        let mut track = result.map_err(explain)?;
 
         if let Some(a) = alias {
             track.alias = Some(a.to_string());
@@ -132,177 +286,253 @@ impl App {
             track.display_name(),
             track.format_duration()
         );
+        if let Some(format) = unplayable_format(&track) {
+            warn_unplayable(&format);
+        }
 
-        Ok(())
+        self.enforce_quota(&[track.id])
     }
 
-    pub fn remove(&self, query: &str) -> Result<()> {
-        let track = self.find_track(query)?;
-
-        // Remove audio file
-        let path = Path::new(&track.file_path);
-        if path.exists() {
-            fs::remove_file(path)?;
+    fn add_collection(
+        &self,
+        downloader: &Downloader,
+        url: &str,
+        save_playlist: bool,
+    ) -> Result<()> {
+        println!("Fetching playlist entries...");
+        let info = downloader.get_playlist_info(url)?;
+
+        if info.entries.is_empty() {
+            println!("No videos found in '{}'.", info.title);
+            return Ok(());
         }
 
-        self.db.delete_track(&track.id)?;
-        println!("Removed: {}", track.display_name());
+        println!("Found {} videos in '{}'", info.entries.len(), info.title);
 
-        Ok(())
-    }
+        let playlist = if save_playlist {
+            Some(self.db.get_or_create_playlist(&info.title)?)
+        } else {
+            None
+        };
 
-    pub fn play(&self, query: &str) -> Result<()> {
-        let track = self.find_track(query)?;
+        let total = info.entries.len();
+        let mut added = Vec::new();
+        let mut skipped = 0;
+        let mut failures = Vec::new();
+        let mut unplayable = None;
 
-        if !track.available {
-            bail!(
-                "Track '{}' is marked as unavailable. Run 'mixyt check' to verify.",
-                track.display_name()
-            );
-        }
+        for (i, entry) in info.entries.iter().enumerate() {
+            let counter = format!("[{}/{}]", i + 1, total);
 
-        let client = self.ensure_daemon()?;
-        match client.play(track.clone())? {
-            DaemonResponse::Ok => {
-                println!(
-                    "Playing: {} ({})",
-                    track.display_name(),
-                    track.format_duration()
-                );
+            let track = if let Some(existing) = self.db.get_track_by_source(&entry.url)? {
+                println!("{counter} Already in library: {}", existing.display_name());
+                skipped += 1;
+                existing
+            } else {
+                println!("{counter} Downloading: {}", entry.title);
+                let result = downloader
+                    .download(&entry.url, None, print_download_progress)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|track| self.db.insert_track(&track).map(|_| track));
+                eprintln!();
+
+                match result {
+                    Ok(track) => {
+                        added.push(track.id);
+                        if unplayable.is_none() {
+                            unplayable = unplayable_format(&track);
+                        }
+                        track
+                    }
+                    Err(e) => {
+                        println!("{counter} Failed: {}", entry.title);
+                        failures.push((entry.title.clone(), e));
+                        continue;
+                    }
+                }
+            };
+
+            if let Some(ref playlist) = playlist {
+                self.db.add_track_to_playlist(&playlist.id, &track.id)?;
             }
-            DaemonResponse::Error(e) => bail!("{e}"),
-            _ => {}
         }
 
-        Ok(())
-    }
+        println!(
+            "\nAdded: {}, Skipped (already exist): {skipped}, Failed: {}",
+            added.len(),
+            failures.len()
+        );
+        for (title, e) in &failures {
+            println!("  [!] {title} - {e}");
+        }
+        if let Some(playlist) = playlist {
+            println!("Saved to playlist: {}", playlist.name);
+        }
+        if let Some(format) = unplayable {
+            warn_unplayable(&format);
+        }
 
-    pub fn pause(&self) -> Result<()> {
-        let client = self.ensure_daemon()?;
-        client.pause()?;
-        println!("Paused");
-        Ok(())
+        self.enforce_quota(&added)
     }
 
-    pub fn resume(&self) -> Result<()> {
-        let client = self.ensure_daemon()?;
-        client.resume()?;
-        println!("Resumed");
-        Ok(())
-    }
+    pub fn add_batch(&self, source: &str, jobs: Option<usize>) -> Result<()> {
+        let mut entries = read_batch(source)?;
+        report_duplicates(dedupe_batch(&mut entries));
 
-    pub fn stop(&self) -> Result<()> {
-        let client = self.ensure_daemon()?;
-        client.stop()?;
-        println!("Stopped");
-        Ok(())
-    }
+        if entries.is_empty() {
+            println!("No URLs to add.");
+            return Ok(());
+        }
 
-    pub fn seek(&self, position: &str) -> Result<()> {
-        let seconds = parse_time(position)?;
-        let client = self.ensure_daemon()?;
-        client.seek(seconds)?;
-        println!("Seeked to {}", format_duration(seconds));
-        Ok(())
-    }
+        let downloader = Downloader::new(self.config.clone());
+        downloader.check_dependencies(entries.iter().map(|e| e.url.as_str()))?;
 
-    pub fn volume(&self, level: Option<u8>) -> Result<()> {
-        let client = self.ensure_daemon()?;
+        let db_path = self.config.db_path();
+        let jobs = jobs
+            .unwrap_or(self.config.download.concurrency)
+            .clamp(1, entries.len());
 
-        if let Some(vol) = level {
-            let vol = vol.min(100);
-            client.set_volume(vol)?;
-            println!("Volume: {vol}%");
-        } else {
-            let status = client.get_status()?;
-            println!("Volume: {}%", status.volume);
+        println!("Adding {} tracks ({} at a time)...", entries.len(), jobs);
+
+        let next = AtomicUsize::new(0);
+        let claimed = Mutex::new(HashSet::new());
+        let (tx, rx) = mpsc::channel();
+        let mut progress = BatchProgress::new(entries.len());
+        let mut added = Vec::new();
+        let mut skipped = 0;
+        let mut failures = Vec::new();
+        let mut unplayable = None;
+
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                let tx = tx.clone();
+                let (next, claimed, entries, downloader, db_path) =
+                    (&next, &claimed, &entries, &downloader, &db_path);
+                scope.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(entry) = entries.get(index) else {
+                            break;
+                        };
+                        let outcome =
+                            download_batch_entry(downloader, db_path, claimed, index, entry, &tx);
+                        let _ = tx.send(BatchUpdate::Done(index, outcome));
+                    }
+                });
+            }
+            drop(tx);
+
+            for update in rx {
+                match update {
+                    BatchUpdate::Started(index, title) => progress.start(index, &title),
+                    BatchUpdate::Progress(index, phase) => progress.progress(index, phase),
+                    BatchUpdate::Done(index, outcome) => {
+                        let entry = &entries[index];
+                        let result = match outcome {
+                            BatchOutcome::Added(mut track) => {
+                                track.alias = entry.alias.clone();
+                                self.db.insert_track(&track).map(|_| (track, true))
+                            }
+                            BatchOutcome::Skipped(track) => Ok((track, false)),
+                            BatchOutcome::Duplicate(url) => {
+                                skipped += 1;
+                                progress.finish(index, &format!("Already in this batch: {url}"));
+                                continue;
+                            }
+                            BatchOutcome::Failed(e) => Err(e),
+                        }
+                        .and_then(|(track, is_new)| {
+                            if let Some(ref name) = entry.playlist {
+                                let playlist = self.db.get_or_create_playlist(name)?;
+                                self.db.add_track_to_playlist(&playlist.id, &track.id)?;
+                            }
+                            Ok((track, is_new))
+                        });
+
+                        match result {
+                            Ok((track, true)) => {
+                                added.push(track.id);
+                                if unplayable.is_none() {
+                                    unplayable = unplayable_format(&track);
+                                }
+                                progress.finish(index, &format!("Added: {}", track.display_name()));
+                            }
+                            Ok((track, false)) => {
+                                skipped += 1;
+                                progress.finish(
+                                    index,
+                                    &format!("Already in library: {}", track.display_name()),
+                                );
+                            }
+                            Err(e) => {
+                                progress.finish(index, &format!("Failed: {}", entry.url));
+                                failures.push((entry.url.clone(), e));
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        println!(
+            "\nSucceeded: {}, Skipped (already exist): {skipped}, Failed: {}",
+            added.len(),
+            failures.len()
+        );
+        for (url, e) in &failures {
+            println!("  [!] {url} - {e}");
+        }
+        if let Some(format) = unplayable {
+            warn_unplayable(&format);
+        }
+        self.enforce_quota(&added)?;
+
+        if !failures.is_empty() {
+            bail!("{} of {} downloads failed", failures.len(), entries.len());
         }
 
         Ok(())
     }
 
-    pub fn list(&self) -> Result<()> {
-        let tracks = self.db.get_all_tracks()?;
-
-        if tracks.is_empty() {
-            println!("No tracks found.");
-            return Ok(());
+    pub fn add_background(&self, url: &str, alias: Option<&str>) -> Result<()> {
+        if is_collection_url(url) {
+            bail!("Playlist and channel URLs can't be queued. Use: mixyt add {url}");
         }
 
-        println!("{} tracks:\n", tracks.len());
-        for (i, track) in tracks.iter().enumerate() {
-            let status = if !track.available {
-                " [unavailable]"
-            } else {
-                ""
-            };
-            let alias = track
-                .alias
-                .as_ref()
-                .map(|a| format!(" ({a})"))
-                .unwrap_or_default();
-            println!(
-                "{:3}. {}{} - {}{}",
-                i + 1,
-                track.title,
-                alias,
-                track.format_duration(),
-                status
-            );
+        if let Some(a) = alias {
+            self.check_alias_free(a)?;
         }
 
+        let client = self.ensure_daemon()?;
+        let job =
+            client.enqueue_download(resolve_source(url), alias.map(|a| a.to_string()), None)?;
+        println!("Queued download {}: {}", job.short_id(), job.url);
+        println!("Check progress with: mixyt downloads");
+
         Ok(())
     }
 
-    pub fn search(&self, query: &str) -> Result<()> {
-        let tracks = self.db.get_all_tracks()?;
-        let matcher = SkimMatcherV2::default();
-
-        let mut matches: Vec<_> = tracks
-            .iter()
-            .filter_map(|track| {
-                let title_score = matcher.fuzzy_match(&track.title, query).unwrap_or(0);
-                let alias_score = track
-                    .alias
-                    .as_ref()
-                    .and_then(|a| matcher.fuzzy_match(a, query))
-                    .unwrap_or(0);
-                let score = title_score.max(alias_score);
-                if score > 0 {
-                    Some((track, score))
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        matches.sort_by(|a, b| b.1.cmp(&a.1));
+    pub fn add_batch_background(&self, source: &str) -> Result<()> {
+        let mut entries = read_batch(source)?;
+        report_duplicates(dedupe_batch(&mut entries));
 
-        if matches.is_empty() {
-            println!("No matches found for '{query}'");
+        if entries.is_empty() {
+            println!("No URLs to add.");
             return Ok(());
         }
 
-        println!("Search results for '{query}':\n");
-        for (i, (track, _score)) in matches.iter().take(10).enumerate() {
-            let alias = track
-                .alias
-                .as_ref()
-                .map(|a| format!(" ({a})"))
-                .unwrap_or_default();
-            println!(
-                "{:3}. {}{} - {}",
-                i + 1,
-                track.title,
-                alias,
-                track.format_duration()
-            );
+        let client = self.ensure_daemon()?;
+        for entry in entries {
+            let job =
+                client.enqueue_download(resolve_source(&entry.url), entry.alias, entry.playlist)?;
+            println!("Queued download {}: {}", job.short_id(), job.url);
         }
+        println!("Check progress with: mixyt downloads");
 
         Ok(())
     }
 
-    pub fn status(&self) -> Result<()> {
+    pub fn downloads(&self) -> Result<()> {
         let client = self.client();
 
         if !client.is_daemon_running() {
@@ -310,57 +540,1290 @@ impl App {
             return Ok(());
         }
 
-        let status = client.get_status()?;
-        print_status(&status);
+        let jobs = client.list_downloads()?;
+
+        if jobs.is_empty() {
+            println!("No downloads.");
+            return Ok(());
+        }
+
+        println!("{} downloads:\n", jobs.len());
+        for job in &jobs {
+            let detail = match (&job.progress, &job.error) {
+                (Some(progress), _) => format!(" {progress}"),
+                (None, Some(error)) => format!(" ({error})"),
+                (None, None) => String::new(),
+            };
+            println!(
+                "  {}  {:<9}  {}{}",
+                job.short_id(),
+                job.status,
+                job.display_name(),
+                detail
+            );
+        }
 
         Ok(())
     }
 
-    pub fn daemon_start(&self) -> Result<()> {
-        if Daemon::is_running(&self.config) {
-            println!("Daemon is already running.");
-            return Ok(());
+    pub fn download_cancel(&self, id: &str) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        for job in client.cancel_download(id)? {
+            println!("Cancelling: {}", job.display_name());
         }
+        Ok(())
+    }
 
-        Daemon::start_detached(&self.config)?;
-        println!("Daemon started.");
+    pub fn download_retry(&self, id: &str) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        for job in client.retry_download(id)? {
+            println!("Retrying: {}", job.display_name());
+        }
+        Ok(())
+    }
 
+    pub fn download_clear(&self) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        let remaining = client.clear_downloads()?;
+        println!(
+            "Cleared finished downloads ({} remaining).",
+            remaining.len()
+        );
         Ok(())
     }
 
-    pub fn daemon_stop(&self) -> Result<()> {
-        if !Daemon::is_running(&self.config) {
-            println!("Daemon is not running.");
-            return Ok(());
-        }
+    pub fn remove(&self, query: &str) -> Result<()> {
+        let track = self.find_track(query)?;
 
-        Daemon::stop(&self.config)?;
-        println!("Daemon stopped.");
+        storage::trash_track(&self.config, &self.db, &track)?;
+        println!("Moved to trash: {}", track.display_name());
+        println!("Undo with: mixyt trash restore");
 
+        storage::expire_trash(&self.config, &self.db)?;
         Ok(())
     }
 
-    pub fn daemon_status(&self) -> Result<()> {
-        if Daemon::is_running(&self.config) {
-            println!("Daemon is running.");
+    /// Edit a track's metadata. Without any fields given, opens it in `$EDITOR`.
+    pub fn edit(
+        &self,
+        query: &str,
+        title: Option<String>,
+        alias: Option<String>,
+        artist: Option<String>,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let track = self.find_track(query)?;
+
+        let edit = if title.is_none() && alias.is_none() && artist.is_none() && notes.is_none() {
+            edit_in_editor(&track)?
         } else {
-            println!("Daemon is not running.");
+            let current = TrackEdit::from_track(&track);
+            TrackEdit {
+                title: title.unwrap_or(current.title),
+                alias: alias.unwrap_or(current.alias),
+                artist: artist.unwrap_or(current.artist),
+                notes: notes.unwrap_or(current.notes),
+            }
+        };
+        let edited = edit.apply(&track);
+
+        if TrackEdit::from_track(&edited) == TrackEdit::from_track(&track) {
+            println!("No changes.");
+            return Ok(());
         }
+        self.db.update_track_metadata(&edited)?;
+        println!("Updated: {}", edited.display_name());
 
         Ok(())
     }
 
-    pub fn daemon_run(&self) -> Result<()> {
+    /// Set where a track starts and ends playing, or show it without options.
+    pub fn trim(
+        &self,
+        query: &str,
+        start: Option<&str>,
+        end: Option<&str>,
+        clear: bool,
+    ) -> Result<()> {
+        let track = self.find_track(query)?;
+
+        if !clear && start.is_none() && end.is_none() {
+            if track.trim_start.is_none() && track.trim_end.is_none() {
+                println!("{} is not trimmed.", track.display_name());
+            } else {
+                println!(
+                    "{} plays {} to {} ({})",
+                    track.display_name(),
+                    format_duration(track.play_start()),
+                    format_duration(track.play_end()),
+                    track.format_duration()
+                );
+            }
+            return Ok(());
+        }
+
+        let (start, end) = if clear {
+            (None, None)
+        } else {
+            // 0 and the full duration are the same as no trim
+            let start = match start {
+                Some(t) => Some(parse_time(t)?).filter(|&t| t > 0),
+                None => track.trim_start,
+            };
+            let end = match end {
+                Some(t) => {
+                    Some(parse_time(t)?).filter(|&t| track.duration == 0 || t < track.duration)
+                }
+                None => track.trim_end,
+            };
+            (start, end)
+        };
+
+        let trimmed = Track {
+            trim_start: start,
+            trim_end: end,
+            ..track
+        };
+        if let Some(end) = end
+            && end <= trimmed.play_start()
+        {
+            bail!(
+                "The end ({}) must come after the start ({})",
+                format_duration(end),
+                format_duration(trimmed.play_start())
+            );
+        }
+        if trimmed.duration > 0 && trimmed.play_start() >= trimmed.duration {
+            bail!(
+                "The start must come before the end of the track ({})",
+                format_duration(trimmed.duration)
+            );
+        }
+
+        self.db.update_track_trim(&trimmed.id, start, end)?;
+        if clear {
+            println!("Removed trim from: {}", trimmed.display_name());
+        } else {
+            println!(
+                "Trimmed {}: plays {} to {} ({})",
+                trimmed.display_name(),
+                format_duration(trimmed.play_start()),
+                format_duration(trimmed.play_end()),
+                trimmed.format_duration()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn alias_add(&self, query: &str, aliases: &[String]) -> Result<()> {
+        let track = self.find_track(query)?;
+
+        for alias in aliases {
+            let alias = alias.trim();
+            if alias.is_empty() {
+                bail!("Aliases can't be empty");
+            }
+            self.db.add_alias(&track.id, alias)?;
+            println!("Added alias '{alias}' to: {}", track.display_title());
+        }
+
+        Ok(())
+    }
+
+    pub fn alias_remove(&self, aliases: &[String]) -> Result<()> {
+        for alias in aliases {
+            let track = self.db.remove_alias(alias)?;
+            println!("Removed alias '{alias}' from: {}", track.display_title());
+        }
+
+        Ok(())
+    }
+
+    /// List a track's aliases, or every alias in the library.
+    pub fn alias_list(&self, query: Option<&str>) -> Result<()> {
+        if let Some(query) = query {
+            let track = self.find_track(query)?;
+            let aliases = self.db.get_track_aliases(&track.id)?;
+            if aliases.is_empty() {
+                println!("{} has no aliases.", track.display_title());
+                return Ok(());
+            }
+
+            println!("Aliases of {}:", track.display_title());
+            for alias in aliases {
+                let shown = if track.alias.as_ref() == Some(&alias) {
+                    " (shown)"
+                } else {
+                    ""
+                };
+                println!("  {alias}{shown}");
+            }
+            return Ok(());
+        }
+
+        let aliases = self.db.get_all_aliases()?;
+        if aliases.is_empty() {
+            println!("No aliases. Add one with: mixyt alias add <track> <alias>");
+            return Ok(());
+        }
+
+        let tracks: HashMap<Uuid, Track> = self
+            .db
+            .get_all_tracks()?
+            .into_iter()
+            .map(|t| (t.id, t))
+            .collect();
+        let width = aliases.iter().map(|a| a.alias.len()).max().unwrap_or(0);
+        for alias in &aliases {
+            let title = tracks
+                .get(&alias.track_id)
+                .map(|t| t.display_title())
+                .unwrap_or("?");
+            println!("  {:width$}  {title}", alias.alias);
+        }
+
+        Ok(())
+    }
+
+    pub fn trash_list(&self) -> Result<()> {
+        storage::expire_trash(&self.config, &self.db)?;
+        let trash = self.db.get_trash()?;
+
+        if trash.is_empty() {
+            println!("Trash is empty.");
+            return Ok(());
+        }
+
+        println!(
+            "{} tracks in the trash ({}):\n",
+            trash.len(),
+            format_size(storage::trash_size(&self.config, &trash))
+        );
+        for trashed in &trash {
+            println!(
+                "  {} - removed {}",
+                trashed.track.display_name(),
+                trashed.deleted_at.format("%Y-%m-%d %H:%M")
+            );
+        }
+        if self.config.storage.trash_days > 0 {
+            println!(
+                "\nTracks are deleted for good after {} days.",
+                self.config.storage.trash_days
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Restore a trashed track, the most recently removed one by default.
+    pub fn trash_restore(&self, query: Option<&str>) -> Result<()> {
+        let trash = self.db.get_trash()?;
+        if trash.is_empty() {
+            bail!("Trash is empty.");
+        }
+
+        let trashed = match query {
+            Some(query) => {
+                let tracks: Vec<Track> = trash.iter().map(|t| t.track.clone()).collect();
+                let Some(track) = match_track(&tracks, query) else {
+                    bail!("No track in the trash matching '{query}'");
+                };
+                trash.iter().find(|t| t.track.id == track.id).unwrap()
+            }
+            None => &trash[0],
+        };
+
+        storage::restore_trashed(&self.config, &self.db, trashed)?;
+        println!("Restored: {}", trashed.track.display_name());
+
+        Ok(())
+    }
+
+    pub fn trash_empty(&self, yes: bool) -> Result<()> {
+        let trash = self.db.get_trash()?;
+        if trash.is_empty() {
+            println!("Trash is empty.");
+            return Ok(());
+        }
+
+        let size = storage::trash_size(&self.config, &trash);
+        let question = format!(
+            "Permanently delete {} tracks ({})?",
+            trash.len(),
+            format_size(size)
+        );
+        if !yes && !confirm(&question)? {
+            println!("Nothing changed.");
+            return Ok(());
+        }
+
+        let deleted = storage::empty_trash(&self.config, &self.db, None)?;
+        println!(
+            "Deleted {} tracks, freed {}.",
+            deleted.len(),
+            format_size(size)
+        );
+
+        Ok(())
+    }
+
+    pub fn play(&self, query: &str, save: bool) -> Result<()> {
+        if query.contains("://") {
+            return self.play_url(query, save);
+        }
+        if save {
+            bail!("--save only applies when playing a URL");
+        }
+
+        let (track, chapter) = self.find_track_or_chapter(query)?;
+        let Some(index) = chapter else {
+            return self.play_track(track);
+        };
+
+        // A seek is handled after the play, unlike a chapter command,
+        // which needs the track to be playing already
+        let chapter = track.chapters[index].clone();
+        self.play_track(track)?;
+        self.client().seek(chapter.start)?;
+        println!("From chapter {}: {}", index + 1, chapter.title);
+        Ok(())
+    }
+
+    /// Like `find_track`, but a chapter title can match too, in which case
+    /// its index comes with the track.
+    fn find_track_or_chapter(&self, query: &str) -> Result<(Track, Option<usize>)> {
+        if let Some(track) = self.db.get_track_by_alias(query)? {
+            return Ok((track, None));
+        }
+
+        let tracks = self.db.get_all_tracks()?;
+        if tracks.is_empty() {
+            bail!("Library is empty. Add tracks with: mixyt add <url>");
+        }
+
+        match match_track_or_chapter(&tracks, query) {
+            Some((track, chapter)) => Ok((track.clone(), chapter)),
+            None => bail!("No track found matching '{query}'"),
+        }
+    }
+
+    /// Stream a URL that isn't in the library, without waiting for the
+    /// download. With `save` it is added to the library once downloaded.
+    fn play_url(&self, url: &str, save: bool) -> Result<()> {
+        if is_collection_url(url) {
+            bail!("Playlists and channels can't be streamed. Add them with: mixyt add {url}");
+        }
+
+        let downloader = Downloader::new(self.config.clone());
+        downloader.check_dependencies([url])?;
+        let (title, canonical_url, duration) = downloader.get_video_info(url).map_err(explain)?;
+
+        if let Some(existing) = self.db.get_track_by_source(&canonical_url)? {
+            return self.play_track(existing);
+        }
+
+        let mut track = Track::new(canonical_url, title, duration, String::new());
+        if let Some(start) = extract_start_time(url) {
+            track.set_bookmark(START_BOOKMARK, start);
+        }
+        let client = self.ensure_daemon()?;
+        match client.play_stream(track.clone(), save)? {
+            DaemonResponse::Ok => {
+                println!("Streaming: {} ({})", track.title, track.format_duration());
+                if save {
+                    println!("It will be added to the library once downloaded.");
+                }
+            }
+            DaemonResponse::Error(e) => bail!("{e}"),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn play_track(&self, mut track: Track) -> Result<()> {
+        if track.remote_only {
+            if track.source_status == SourceStatus::Unavailable {
+                bail!(
+                    "Track '{}' was evicted and its source is no longer available.",
+                    track.display_name()
+                );
+            }
+
+            eprintln!("Downloading evicted track: {}", track.display_name());
+            let downloader = Downloader::new(self.config.clone());
+            let result =
+                storage::restore_track(&downloader, &self.db, &track, print_download_progress);
+            eprintln!();
+            track = result?;
+            self.enforce_quota(&[track.id])?;
+        } else {
+            // The local file is all playback needs, whatever happened to the
+            // source. The stored state may be out of date either way
+            let exists = self.config.resolve_audio_path(&track.file_path).exists();
+            let status = if exists {
+                FileStatus::Present
+            } else {
+                FileStatus::Missing
+            };
+            if status != track.file_status {
+                self.db
+                    .update_track_file_status(&track.id, status, Utc::now())?;
+            }
+            if !exists {
+                bail!(
+                    "The audio file of '{}' is missing. Run 'mixyt gc' to find it or have it downloaded again.",
+                    track.display_name()
+                );
+            }
+        }
+
+        let client = self.ensure_daemon()?;
+        match client.play(track.clone())? {
+            DaemonResponse::Ok => {
+                println!(
+                    "Playing: {} ({})",
+                    track.display_name(),
+                    track.format_duration()
+                );
+            }
+            DaemonResponse::Error(e) => bail!("{e}"),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        client.pause()?;
+        println!("Paused");
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        client.resume()?;
+        println!("Resumed");
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        client.stop()?;
+        println!("Stopped");
+        Ok(())
+    }
+
+    pub fn seek(&self, position: &str) -> Result<()> {
+        let seconds = parse_time(position)?;
+        let client = self.ensure_daemon()?;
+        client.seek(seconds)?;
+        println!("Seeked to {}", format_duration(seconds));
+        Ok(())
+    }
+
+    pub fn next_chapter(&self) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        expect_ok(client.next_chapter()?)?;
+        print_chapter(&client.get_status()?);
+        Ok(())
+    }
+
+    pub fn previous_chapter(&self) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        expect_ok(client.previous_chapter()?)?;
+        print_chapter(&client.get_status()?);
+        Ok(())
+    }
+
+    /// Jump to a chapter of the current track by number or title.
+    pub fn chapter(&self, chapter: &str) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        let status = client.get_status()?;
+        let Some(track) = status.current_track else {
+            bail!("Nothing is playing");
+        };
+
+        let index = match chapter.parse::<usize>() {
+            Ok(0) => bail!("Chapters are numbered from 1"),
+            Ok(n) => n - 1,
+            Err(_) => match_chapter(&track, chapter)
+                .map(|(i, _)| i)
+                .with_context(|| {
+                    format!(
+                        "No chapter of '{}' matches '{chapter}'",
+                        track.display_name()
+                    )
+                })?,
+        };
+
+        expect_ok(client.chapter(index)?)?;
+        println!("Chapter {}: {}", index + 1, track.chapters[index].title);
+        Ok(())
+    }
+
+    /// List the chapters of a track, or of the one playing. With `refresh`
+    /// they are fetched from the source again.
+    pub fn chapters(&self, query: Option<&str>, refresh: bool) -> Result<()> {
+        let (mut track, current) = match query {
+            Some(q) => (self.find_track(q)?, None),
+            None => {
+                let status = self.ensure_daemon()?.get_status()?;
+                let chapter = status.chapter;
+                match status.current_track {
+                    Some(track) => (track, chapter),
+                    None => bail!("Nothing is playing. Name a track: mixyt chapters <query>"),
+                }
+            }
+        };
+
+        if refresh {
+            let downloader = Downloader::new(self.config.clone());
+            track.chapters = downloader.get_chapters(&track.url).map_err(explain)?;
+            self.db.update_track_chapters(&track.id, &track.chapters)?;
+        }
+
+        if track.chapters.is_empty() {
+            println!("{} has no chapters.", track.display_name());
+            if !refresh {
+                let query = query.map(|q| format!(" \"{q}\"")).unwrap_or_default();
+                println!("Fetch them from the source with: mixyt chapters{query} --refresh");
+            }
+            return Ok(());
+        }
+
+        println!("Chapters of {}:\n", track.display_name());
+        for (i, chapter) in track.chapters.iter().enumerate() {
+            let marker = if current == Some(i) { ">" } else { " " };
+            println!(
+                "{marker}{:3}. {} {}",
+                i + 1,
+                format_duration(chapter.start),
+                chapter.title
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The playing track as stored in the library, with its current marks.
+    fn playing_track(&self, client: &DaemonClient) -> Result<Option<(Track, u64)>> {
+        let status = client.get_status()?;
+        let Some(playing) = status.current_track else {
+            return Ok(None);
+        };
+        match self.db.get_track(&playing.id)? {
+            Some(track) => Ok(Some((track, status.position))),
+            None => bail!("'{}' isn't in the library", playing.display_name()),
+        }
+    }
+
+    /// Bookmark the current position of the playing track, or `at` a time.
+    pub fn mark(&self, name: &str, at: &[String], delete: bool) -> Result<()> {
+        let time = match at {
+            [] => None,
+            [word, time] if word == "at" => Some(parse_time(time)?),
+            [time] => Some(parse_time(time)?),
+            _ => bail!("Use: mixyt mark <name> [at <time>]"),
+        };
+
+        let client = self.ensure_daemon()?;
+        let Some((mut track, position)) = self.playing_track(&client)? else {
+            bail!("Nothing is playing");
+        };
+
+        if delete {
+            let Some(index) = track.bookmarks.iter().position(|b| b.name == name) else {
+                bail!("'{}' has no mark '{name}'", track.display_name());
+            };
+            track.bookmarks.remove(index);
+            self.db
+                .update_track_bookmarks(&track.id, &track.bookmarks)?;
+            println!("Removed mark '{name}' from: {}", track.display_name());
+            return Ok(());
+        }
+
+        let position = time.unwrap_or(position);
+        if track.duration > 0 && position > track.duration {
+            bail!(
+                "{} is past the end of the track ({})",
+                format_duration(position),
+                format_duration(track.duration)
+            );
+        }
+
+        track.set_bookmark(name, position);
+        self.db
+            .update_track_bookmarks(&track.id, &track.bookmarks)?;
+        println!(
+            "Marked '{name}' at {} in: {}",
+            format_duration(position),
+            track.display_name()
+        );
+
+        Ok(())
+    }
+
+    /// List the marks of a track, the playing one, or every marked track.
+    pub fn marks(&self, query: Option<&str>) -> Result<()> {
+        let tracks = match query {
+            Some(q) => vec![self.find_track(q)?],
+            None => {
+                let client = self.client();
+                let playing = if client.is_daemon_running() {
+                    self.playing_track(&client)?
+                } else {
+                    None
+                };
+                match playing {
+                    Some((track, _)) => vec![track],
+                    None => self
+                        .db
+                        .get_all_tracks()?
+                        .into_iter()
+                        .filter(|t| !t.bookmarks.is_empty())
+                        .collect(),
+                }
+            }
+        };
+
+        match tracks.as_slice() {
+            [] => println!("No marks. Add one while playing with: mixyt mark <name>"),
+            [track] if track.bookmarks.is_empty() => {
+                println!("{} has no marks.", track.display_name());
+            }
+            _ => {
+                for track in &tracks {
+                    println!("{}:", track.display_name());
+                    for (i, mark) in track.bookmarks.iter().enumerate() {
+                        println!(
+                            "  {:2}. {:>6}  {}",
+                            i + 1,
+                            format_duration(mark.position),
+                            mark.name
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Seek to a mark of the playing track. A mark only one other track
+    /// has plays that track from it.
+    pub fn goto(&self, name: &str) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        if let Some((track, _)) = self.playing_track(&client)?
+            && let Some(mark) = track.bookmark(name)
+        {
+            expect_ok(client.seek(mark.position)?)?;
+            println!(
+                "Seeked to '{}' at {}",
+                mark.name,
+                format_duration(mark.position)
+            );
+            return Ok(());
+        }
+
+        let marked: Vec<Track> = self
+            .db
+            .get_all_tracks()?
+            .into_iter()
+            .filter(|t| t.bookmark(name).is_some())
+            .collect();
+        let track = match marked.as_slice() {
+            [] => bail!("No mark named '{name}'. See them with: mixyt marks"),
+            [track] => track.clone(),
+            _ => {
+                let names: Vec<&str> = marked.iter().map(|t| t.display_name()).collect();
+                bail!(
+                    "Several tracks have a mark '{name}': {}. Play one first.",
+                    names.join(", ")
+                );
+            }
+        };
+
+        let position = track.bookmark(name).map(|m| m.position).unwrap_or_default();
+        self.play_track(track)?;
+        expect_ok(self.client().seek(position)?)?;
+        println!("From '{name}' at {}", format_duration(position));
+        Ok(())
+    }
+
+    /// Show, set or clear the A-B loop of the playing track.
+    pub fn ab_loop(&self, a: Option<&str>, b: Option<&str>) -> Result<()> {
+        let client = self.ensure_daemon()?;
+        let (a, b) = match (a, b) {
+            (None, _) => {
+                match client.get_status()?.loop_region {
+                    Some(region) => println!("Loop: {}", format_loop(region)),
+                    None => println!("Not looping. Set a loop with: mixyt loop <a> <b>"),
+                }
+                return Ok(());
+            }
+            (Some("off"), None) => {
+                expect_ok(client.set_loop(None)?)?;
+                println!("Loop off");
+                return Ok(());
+            }
+            (Some(a), Some(b)) => (a, b),
+            (Some(_), None) => bail!("Use: mixyt loop <a> <b>, or mixyt loop off"),
+        };
+
+        let Some((track, _)) = self.playing_track(&client)? else {
+            bail!("Nothing is playing");
+        };
+        let point = |s: &str| match track.bookmark(s) {
+            Some(mark) => Ok(std::time::Duration::from_secs(mark.position)),
+            None => parse_precise_time(s),
+        };
+        let region = LoopRegion {
+            start: point(a)?,
+            end: point(b)?,
+        };
+
+        expect_ok(client.set_loop(Some(region))?)?;
+        println!(
+            "Looping {} in: {}",
+            format_loop(region),
+            track.display_name()
+        );
+        Ok(())
+    }
+
+    pub fn volume(&self, level: Option<u8>) -> Result<()> {
+        let client = self.ensure_daemon()?;
+
+        if let Some(vol) = level {
+            let vol = vol.min(100);
+            client.set_volume(vol)?;
+            println!("Volume: {vol}%");
+        } else {
+            let status = client.get_status()?;
+            println!("Volume: {}%", status.volume);
+        }
+
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<()> {
+        let tracks = self.db.get_all_tracks()?;
+
+        if tracks.is_empty() {
+            println!("No tracks found.");
+            return Ok(());
+        }
+
+        println!("{} tracks:\n", tracks.len());
+        for (i, track) in tracks.iter().enumerate() {
+            let file_status = if track.remote_only {
+                " [remote only]"
+            } else if track.file_status == FileStatus::Missing {
+                " [file missing]"
+            } else {
+                ""
+            };
+            let source_status = if track.source_status == SourceStatus::Unavailable {
+                " [source unavailable]"
+            } else {
+                ""
+            };
+            let alias = track
+                .alias
+                .as_ref()
+                .map(|a| format!(" ({a})"))
+                .unwrap_or_default();
+            println!(
+                "{:3}. {}{} - {}{}{}",
+                i + 1,
+                track.display_title(),
+                alias,
+                track.format_duration(),
+                file_status,
+                source_status
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn search(&self, query: &str) -> Result<()> {
+        let tracks = self.db.get_all_tracks()?;
+        let matcher = SkimMatcherV2::default();
+
+        let mut matches: Vec<_> = tracks
+            .iter()
+            .filter_map(|track| {
+                let (chapter, score) = match_score_with_chapters(&matcher, track, query)?;
+                Some((track, chapter, score))
+            })
+            .collect();
+
+        matches.sort_by_key(|m| std::cmp::Reverse(m.2));
+
+        if matches.is_empty() {
+            println!("No matches found for '{query}'");
+            return Ok(());
+        }
+
+        println!("Search results for '{query}':\n");
+        for (i, (track, chapter, _score)) in matches.iter().take(10).enumerate() {
+            let alias = track
+                .alias
+                .as_ref()
+                .map(|a| format!(" ({a})"))
+                .unwrap_or_default();
+            println!(
+                "{:3}. {}{} - {}",
+                i + 1,
+                track.display_title(),
+                alias,
+                track.format_duration()
+            );
+            if let Some(index) = *chapter {
+                let chapter = &track.chapters[index];
+                println!(
+                    "       chapter {}: {} at {}",
+                    index + 1,
+                    chapter.title,
+                    format_duration(chapter.start)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn status(&self) -> Result<()> {
+        let client = self.client();
+
+        if !client.is_daemon_running() {
+            println!("Daemon is not running.");
+            return Ok(());
+        }
+
+        let status = client.get_status()?;
+        print_status(&status);
+
+        Ok(())
+    }
+
+    pub fn daemon_start(&self) -> Result<()> {
+        if Daemon::is_running(&self.config) {
+            println!("Daemon is already running.");
+            return Ok(());
+        }
+
+        Daemon::start_detached(&self.config)?;
+        println!("Daemon started.");
+
+        Ok(())
+    }
+
+    pub fn daemon_stop(&self) -> Result<()> {
+        if !Daemon::is_running(&self.config) {
+            println!("Daemon is not running.");
+            return Ok(());
+        }
+
+        Daemon::stop(&self.config)?;
+        println!("Daemon stopped.");
+
+        Ok(())
+    }
+
+    pub fn daemon_status(&self) -> Result<()> {
+        if Daemon::is_running(&self.config) {
+            println!("Daemon is running.");
+        } else {
+            println!("Daemon is not running.");
+        }
+
+        Ok(())
+    }
+
+    pub fn daemon_run(&self) -> Result<()> {
         let daemon = Daemon::new(self.config.clone())?;
         daemon.run()
     }
 
+    pub fn du(&self, limit: usize) -> Result<()> {
+        let tracks = self.db.get_all_tracks()?;
+        let total = storage::library_size(&self.config, &tracks);
+        let remote = tracks.iter().filter(|t| t.remote_only).count();
+
+        println!(
+            "Library: {} in {} tracks",
+            format_size(total),
+            tracks.len() - remote
+        );
+        if remote > 0 {
+            println!("Remote only: {remote} tracks (downloaded again when played)");
+        }
+        let trash = self.db.get_trash()?;
+        if !trash.is_empty() {
+            println!(
+                "Trash: {} in {} tracks (empty it with: mixyt trash empty)",
+                format_size(storage::trash_size(&self.config, &trash)),
+                trash.len()
+            );
+        }
+        if let Some(max_size) = self.config.storage.max_size_bytes()? {
+            println!(
+                "Quota: {} of {} ({}%)",
+                format_size(total),
+                format_size(max_size),
+                (total * 100).checked_div(max_size).unwrap_or(100)
+            );
+        }
+
+        let mut sizes: Vec<_> = tracks
+            .iter()
+            .filter_map(|t| storage::track_size(&self.config, t).map(|size| (t, size)))
+            .collect();
+        sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+
+        if !sizes.is_empty() {
+            println!("\nLargest tracks:");
+            for (track, size) in sizes.iter().take(limit) {
+                println!("  {:>9}  {}", format_size(*size), track.display_name());
+            }
+        }
+
+        let mut playlists = Vec::new();
+        for playlist in self.db.get_all_playlists()? {
+            let tracks = self.db.get_playlist_tracks(&playlist.id)?;
+            let size = storage::library_size(&self.config, &tracks);
+            playlists.push((playlist, tracks.len(), size));
+        }
+        playlists.sort_by_key(|(_, _, size)| std::cmp::Reverse(*size));
+
+        if !playlists.is_empty() {
+            println!("\nLargest playlists:");
+            for (playlist, count, size) in playlists.iter().take(limit) {
+                println!(
+                    "  {:>9}  {} ({count} tracks)",
+                    format_size(*size),
+                    playlist.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn gc(&self, dry_run: bool, yes: bool) -> Result<()> {
+        // Streams and queued downloads belong to the daemon while it runs
+        let client = self.client();
+        let (include_streams, active_jobs) = if client.is_daemon_running() {
+            let jobs = client.list_downloads()?;
+            (
+                false,
+                jobs.into_iter()
+                    .filter(|j| !j.status.is_finished())
+                    .collect(),
+            )
+        } else {
+            (true, Vec::new())
+        };
+        let garbage = storage::find_garbage(&self.config, &self.db, include_streams, &active_jobs)?;
+
+        if garbage.is_empty() {
+            println!("Nothing to clean up.");
+            return Ok(());
+        }
+
+        let print_files = |heading: &str, files: &[(PathBuf, u64)]| {
+            if files.is_empty() {
+                return;
+            }
+            let size = files.iter().map(|(_, size)| size).sum();
+            println!("{heading} ({}, {}):", files.len(), format_size(size));
+            for (path, size) in files {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                println!("  {:>9}  {name}", format_size(*size));
+            }
+        };
+        print_files("Temporary files", &garbage.temp_files);
+        print_files("Audio files not in the library", &garbage.orphan_files);
+
+        if !garbage.missing.is_empty() {
+            println!("Tracks with missing audio ({}):", garbage.missing.len());
+            for (track, relink) in &garbage.missing {
+                match relink {
+                    Some(path) => println!(
+                        "  {} -> re-link to {}",
+                        track.display_name(),
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ),
+                    None => println!(
+                        "  {} -> remote only (downloaded again when played)",
+                        track.display_name()
+                    ),
+                }
+            }
+        }
+        if !garbage.dangling_entries.is_empty() {
+            println!(
+                "Playlist entries for deleted tracks: {}",
+                garbage.dangling_entries.len()
+            );
+        }
+
+        println!("\n{} can be freed.", format_size(garbage.size()));
+        if dry_run {
+            return Ok(());
+        }
+
+        if !yes && !confirm("Clean up?")? {
+            println!("Nothing changed.");
+            return Ok(());
+        }
+
+        storage::collect_garbage(&self.config, &self.db, &garbage)?;
+        println!("Cleaned up, freed {}.", format_size(garbage.size()));
+
+        Ok(())
+    }
+
+    /// Merge tracks that are the same video under different URLs.
+    pub fn dedupe(&self, dry_run: bool, yes: bool) -> Result<()> {
+        let found = storage::find_duplicates(&self.config, &self.db)?;
+
+        if found.is_empty() {
+            println!("No duplicate tracks.");
+            return Ok(());
+        }
+
+        println!("Found {} videos added more than once:", found.len());
+        for group in &found {
+            println!("  {} (kept)", group.keep.display_name());
+            for duplicate in &group.duplicates {
+                println!("    - {} ({})", duplicate.display_name(), duplicate.url);
+            }
+        }
+
+        if dry_run {
+            return Ok(());
+        }
+        if !yes && !confirm("Merge them?")? {
+            println!("Nothing changed.");
+            return Ok(());
+        }
+
+        storage::merge_duplicates(&self.config, &self.db, &found)?;
+        let merged: usize = found.iter().map(|g| g.duplicates.len()).sum();
+        println!("Merged {merged} duplicate tracks.");
+
+        Ok(())
+    }
+
+    /// Re-encode tracks to another format, replacing their audio files.
+    pub fn convert(
+        &self,
+        query: Option<&str>,
+        format: Option<&str>,
+        bitrate: Option<&str>,
+        jobs: Option<usize>,
+        force: bool,
+    ) -> Result<()> {
+        let format = match format {
+            Some(f) => f,
+            None if self.config.audio.keeps_original() => {
+                bail!("audio.format is 'original'. Pick a target with --format")
+            }
+            None => &self.config.audio.format,
+        };
+        let target = Target::new(format, bitrate)?;
+
+        let tracks = match query {
+            Some(q) => vec![self.find_track(q)?],
+            None => self.db.get_all_tracks()?,
+        };
+
+        // Replacing the file under the audio thread would cut playback off
+        let client = self.client();
+        let playing = if client.is_daemon_running() {
+            client
+                .get_status()
+                .ok()
+                .and_then(|s| s.current_track)
+                .map(|t| t.id)
+        } else {
+            None
+        };
+
+        let mut pending = Vec::new();
+        for track in tracks {
+            let path = self.config.resolve_audio_path(&track.file_path);
+            if Some(track.id) == playing {
+                println!("Skipping (playing): {}", track.display_name());
+            } else if !path.exists() {
+                println!("Skipping (file missing): {}", track.display_name());
+            } else if !target.matches(&path) {
+                pending.push((track, path));
+            }
+        }
+
+        if pending.is_empty() {
+            println!("Nothing to convert.");
+            return Ok(());
+        }
+
+        // The original is replaced, so don't swap a playable file for one
+        // the player can't decode without being asked to
+        let loses_playback = !audio::can_play(target.extension)
+            && pending.iter().any(|(_, path)| {
+                path.extension()
+                    .is_some_and(|ext| audio::can_play(&ext.to_string_lossy()))
+            });
+        if loses_playback {
+            if !force {
+                bail!(
+                    "The player can't decode {} files (supported: {}). Convert anyway with --force",
+                    target.format,
+                    audio::PLAYABLE_FORMATS.join(", ")
+                );
+            }
+            warn_unplayable(&target.format);
+        }
+
+        let jobs = jobs
+            .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .clamp(1, pending.len());
+
+        println!(
+            "Converting {} tracks to {} ({} at a time)...",
+            pending.len(),
+            target.format,
+            jobs
+        );
+
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+        let mut saved: i64 = 0;
+        let mut converted = 0;
+        let mut failed = 0;
+
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                let tx = tx.clone();
+                let (next, pending, target) = (&next, &pending, &target);
+                scope.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some((_, path)) = pending.get(index) else {
+                            break;
+                        };
+                        let _ = tx.send((index, convert_file(path, target)));
+                    }
+                });
+            }
+            drop(tx);
+
+            for (index, result) in rx {
+                let (track, old_path) = &pending[index];
+                let result = result.and_then(|output| {
+                    let file_path = self.config.relative_audio_path(&output.path);
+                    self.db.update_track_audio(
+                        &track.id,
+                        &file_path,
+                        Some(target.extension),
+                        output.bitrate,
+                    )?;
+                    if output.path != *old_path {
+                        fs::remove_file(old_path)?;
+                    }
+                    Ok(output)
+                });
+
+                match result {
+                    Ok(output) => {
+                        converted += 1;
+                        saved += output.old_size as i64 - output.new_size as i64;
+                        println!(
+                            "Converted: {} ({} -> {})",
+                            track.display_name(),
+                            format_size(output.old_size),
+                            format_size(output.new_size)
+                        );
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        println!("Failed: {} - {e}", track.display_name());
+                    }
+                }
+            }
+        });
+
+        if saved >= 0 {
+            println!(
+                "\nConverted {converted} tracks, saved {}",
+                format_size(saved as u64)
+            );
+        } else {
+            println!(
+                "\nConverted {converted} tracks, using {} more space",
+                format_size(saved.unsigned_abs())
+            );
+        }
+
+        if failed > 0 {
+            bail!("{failed} of {} conversions failed", pending.len());
+        }
+
+        Ok(())
+    }
+
+    /// Move the data directory and point the config at it. Track paths are
+    /// relative to the library, so the database needs no changes.
+    pub fn library_move(self, path: &str) -> Result<()> {
+        if Daemon::is_running(&self.config) {
+            bail!("The daemon is running. Stop it first with: mixyt daemon stop");
+        }
+
+        let old = self.config.data_dir().clone();
+        let new = std::path::absolute(path)?;
+
+        if new == old {
+            bail!("Library is already at {}", new.display());
+        }
+        if new.starts_with(&old) {
+            bail!("Can't move the library into itself");
+        }
+        if new.exists() && fs::read_dir(&new)?.next().is_some() {
+            bail!("{} is not empty", new.display());
+        }
+
+        // Close the database and drop the stale daemon files before moving
+        let App { mut config, db } = self;
+        drop(db);
+        let _ = fs::remove_file(config.socket_path());
+        let _ = fs::remove_file(config.pid_path());
+
+        println!("Moving library to {}...", new.display());
+        move_dir(&old, &new)?;
+
+        config.storage.path = new;
+        config.save()?;
+        println!("Library moved.");
+        println!("Updated config: {}", Config::config_path().display());
+
+        Ok(())
+    }
+
     pub fn export(&self, file: Option<&str>) -> Result<()> {
         let tracks = self.db.get_all_tracks()?;
         let playlists = self.db.get_all_playlists()?;
         let playlist_tracks = self.db.get_all_playlist_tracks()?;
+        let aliases = self.db.get_all_aliases()?;
 
-        let export = LibraryExport::new(tracks, playlists, playlist_tracks);
+        let export = LibraryExport::new(tracks, playlists, playlist_tracks, aliases);
         let json = serde_json::to_string_pretty(&export)?;
 
         if let Some(path) = file {
@@ -388,17 +1851,28 @@ impl App {
         let mut skipped = 0;
 
         for track in import.tracks {
-            if self.db.get_track_by_url(&track.url)?.is_some() {
+            if self.db.get_track_by_source(&track.url)?.is_some() {
                 skipped += 1;
                 continue;
             }
 
-            // Mark as unavailable since we don't have the audio file
+            // Mark the file missing since we don't have the audio file
             let mut track = track;
-            track.available = false;
+            track.file_status = FileStatus::Missing;
+            // Exports from before video IDs were stored lack them
+            track.video_id = track.video_id.or_else(|| extract_video_id(&track.url));
+            // Aliases taken in this library are left off
+            if let Some(ref alias) = track.alias
+                && self.db.get_track_by_alias(alias)?.is_some()
+            {
+                track.alias = None;
+            }
 
             if self.db.insert_track(&track).is_ok() {
                 imported += 1;
+                for alias in import.aliases.iter().filter(|a| a.track_id == track.id) {
+                    let _ = self.db.add_alias(&track.id, &alias.alias);
+                }
             }
         }
 
@@ -414,7 +1888,7 @@ impl App {
         Ok(())
     }
 
-    pub fn check(&self) -> Result<()> {
+    pub fn check(&self, stale: Option<&str>, offline: bool, jobs: Option<usize>) -> Result<()> {
         let tracks = self.db.get_all_tracks()?;
 
         if tracks.is_empty() {
@@ -422,42 +1896,433 @@ impl App {
             return Ok(());
         }
 
-        println!("Checking {} tracks...", tracks.len());
+        self.check_files(&tracks)?;
+        if offline {
+            return Ok(());
+        }
+
+        // Only re-check sources that haven't been reached recently
+        let cutoff = stale
+            .map(parse_age)
+            .transpose()?
+            .map(|age| {
+                Utc::now()
+                    .checked_sub_signed(age)
+                    .context("--stale is too long")
+            })
+            .transpose()?;
+        let due: Vec<&Track> = tracks
+            .iter()
+            .filter(|t| match (cutoff, t.source_checked_at) {
+                (Some(cutoff), Some(checked)) => checked < cutoff,
+                _ => true,
+            })
+            .collect();
+
+        if due.len() < tracks.len() {
+            println!(
+                "\nSkipping {} sources checked in the last {}",
+                tracks.len() - due.len(),
+                stale.unwrap_or_default()
+            );
+        }
+        if due.is_empty() {
+            return Ok(());
+        }
 
         let downloader = Downloader::new(self.config.clone());
+        let jobs = jobs
+            .unwrap_or(self.config.download.concurrency)
+            .clamp(1, due.len());
+        println!("\nChecking {} sources ({} at a time)...", due.len(), jobs);
+
+        let next = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let (tx, rx) = mpsc::channel();
         let mut available = 0;
         let mut unavailable = 0;
+        let mut unknown = 0;
+        let mut network_errors = 0;
+
+        thread::scope(|scope| -> Result<()> {
+            for _ in 0..jobs {
+                let tx = tx.clone();
+                let (next, stop, due, downloader) = (&next, &stop, &due, &downloader);
+                scope.spawn(move || {
+                    while !stop.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(track) = due.get(index) else {
+                            break;
+                        };
+                        let _ = tx.send((index, downloader.check_availability(&track.url)));
+                    }
+                });
+            }
+            drop(tx);
+
+            for (index, source) in rx {
+                let track = due[index];
+
+                // Only a definite answer about the video changes its state;
+                // network problems and the like leave it alone
+                let status = match source {
+                    Ok(()) => SourceStatus::Available,
+                    Err(e) if e.is_unavailable() => {
+                        println!("  [!] {} - {e}", track.display_name());
+                        SourceStatus::Unavailable
+                    }
+                    Err(e) => {
+                        unknown += 1;
+                        println!("  [?] {} - could not check: {e}", track.display_name());
+                        if matches!(e, DownloadError::Network(_)) {
+                            network_errors += 1;
+                        }
+                        // A run of network failures means we're offline
+                        if network_errors >= NETWORK_ERRORS_OFFLINE {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                        continue;
+                    }
+                };
+                network_errors = 0;
 
-        for track in &tracks {
-            // Check if local file exists
-            let file_exists = Path::new(&track.file_path).exists();
+                self.db
+                    .update_track_source_status(&track.id, status, Utc::now())?;
+                if status == SourceStatus::Available {
+                    available += 1;
+                } else {
+                    unavailable += 1;
+                }
+            }
+            Ok(())
+        })?;
+
+        let unchecked = due.len() - available - unavailable;
+        println!("\nSources available: {available}, unavailable: {unavailable}");
+        if stop.load(Ordering::SeqCst) {
+            println!(
+                "The network seems to be down; {unchecked} sources were not checked. \
+                 Use 'mixyt check --offline' to check local files only."
+            );
+        } else if unknown > 0 {
+            println!("Could not check {unknown} sources; run 'mixyt check' again later.");
+        }
+
+        Ok(())
+    }
+
+    /// Check that tracks' audio files exist and record what was found.
+    fn check_files(&self, tracks: &[Track]) -> Result<()> {
+        // Evicted tracks have no file on purpose
+        let local: Vec<&Track> = tracks.iter().filter(|t| !t.remote_only).collect();
+        println!("Checking local files of {} tracks...", local.len());
+        let now = Utc::now();
+        let mut missing = 0;
+
+        for track in &local {
+            let status = if self.config.resolve_audio_path(&track.file_path).exists() {
+                FileStatus::Present
+            } else {
+                missing += 1;
+                println!("  [!] {} - file missing", track.display_name());
+                FileStatus::Missing
+            };
+            self.db.update_track_file_status(&track.id, status, now)?;
+        }
+
+        println!(
+            "\nFiles present: {}, missing: {missing}",
+            local.len() - missing
+        );
+
+        Ok(())
+    }
+
+    pub fn subscribe(&self, url: &str, into: Option<&str>, filter: Option<&str>) -> Result<()> {
+        if !is_collection_url(url) {
+            bail!("Subscriptions need a YouTube channel or playlist URL");
+        }
+
+        if let Some(f) = filter {
+            f.parse::<SyncFilter>().map_err(anyhow::Error::msg)?;
+        }
+
+        if let Some(existing) = self.db.get_subscription_by_url(url)? {
+            println!("Already subscribed to: {}", existing.title);
+            return Ok(());
+        }
+
+        let downloader = Downloader::new(self.config.clone());
+        downloader.check_dependencies([url])?;
+
+        println!("Fetching playlist entries...");
+        let info = downloader.get_playlist_info(url)?;
+
+        let playlist = into
+            .map(|name| self.db.get_or_create_playlist(name))
+            .transpose()?;
+
+        let subscription = Subscription::new(
+            url.to_string(),
+            info.title.clone(),
+            playlist.as_ref().map(|p| p.id),
+            filter.map(|f| f.to_string()),
+        );
+        self.db.insert_subscription(&subscription)?;
+
+        // Only uploads that appear after subscribing are downloaded by sync
+        for entry in &info.entries {
+            self.db
+                .add_subscription_entry(&subscription.id, &entry.id)?;
+        }
+
+        println!("Subscribed to: {}", subscription.title);
+        println!(
+            "Skipping {} existing videos. Use 'mixyt add {url}' to import them.",
+            info.entries.len()
+        );
+
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, query: &str) -> Result<()> {
+        let subscriptions = self.db.get_all_subscriptions()?;
+        let query_lower = query.to_lowercase();
+
+        let Some(subscription) = subscriptions
+            .iter()
+            .find(|s| s.url == query || s.title.to_lowercase().contains(&query_lower))
+        else {
+            bail!("No subscription found matching '{query}'");
+        };
+
+        self.db.delete_subscription(&subscription.id)?;
+        println!("Unsubscribed from: {}", subscription.title);
+
+        Ok(())
+    }
+
+    pub fn subscriptions(&self) -> Result<()> {
+        let subscriptions = self.db.get_all_subscriptions()?;
+
+        if subscriptions.is_empty() {
+            println!("No subscriptions. Add one with: mixyt subscribe <url>");
+            return Ok(());
+        }
+
+        println!("{} subscriptions:\n", subscriptions.len());
+        for (i, subscription) in subscriptions.iter().enumerate() {
+            println!("{:3}. {}", i + 1, subscription.title);
+            println!("     {}", subscription.url);
+
+            if let Some(playlist_id) = subscription.playlist_id
+                && let Some(playlist) = self.db.get_playlist(&playlist_id)?
+            {
+                println!("     Into: {}", playlist.name);
+            }
+            if let Some(ref filter) = subscription.filter {
+                println!("     Filter: {filter}");
+            }
+
+            let synced = subscription
+                .last_synced_at
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "never".to_string());
+            println!("     Last synced: {synced}");
+        }
+
+        Ok(())
+    }
 
-            // Check if URL is still available
-            let url_available = downloader.check_availability(&track.url).unwrap_or(false);
+    pub fn sync(&self) -> Result<()> {
+        let subscriptions = self.db.get_all_subscriptions()?;
 
-            let is_available = file_exists && url_available;
+        if subscriptions.is_empty() {
+            println!("No subscriptions. Add one with: mixyt subscribe <url>");
+            return Ok(());
+        }
 
-            if is_available != track.available {
-                self.db.update_track_availability(&track.id, is_available)?;
+        let downloader = Downloader::new(self.config.clone());
+        downloader.check_dependencies(subscriptions.iter().map(|s| s.url.as_str()))?;
+        let mut total = SyncReport::default();
+
+        for subscription in &subscriptions {
+            println!("Syncing {}...", subscription.title);
+
+            let result =
+                sync_subscription(&downloader, &self.db, subscription, |event| match event {
+                    SyncEvent::Downloading(entry) => println!("  Downloading: {}", entry.title),
+                    SyncEvent::Progress(phase) => print_download_progress(phase),
+                    SyncEvent::Added(track) => {
+                        eprintln!();
+                        println!(
+                            "  Added: {} ({})",
+                            track.display_name(),
+                            track.format_duration()
+                        );
+                    }
+                    SyncEvent::Failed(entry, e) => {
+                        eprintln!();
+                        println!("  [!] {} - {e}", entry.title);
+                    }
+                });
+
+            match result {
+                Ok(report) => {
+                    total.added += report.added;
+                    total.skipped += report.skipped;
+                    total.failed += report.failed;
+                }
+                Err(e) => println!("  [!] Failed to sync: {e}"),
             }
+        }
+
+        println!(
+            "\nAdded: {}, Skipped (already exist): {}, Failed: {}",
+            total.added, total.skipped, total.failed
+        );
 
-            if is_available {
-                available += 1;
+        self.enforce_quota(&[])
+    }
+}
+
+/// The track best matching a query: an exact alias or title, otherwise the
+/// best fuzzy match.
+fn match_track<'a>(tracks: &'a [Track], query: &str) -> Option<&'a Track> {
+    if let Some(track) = match_exact(tracks, query) {
+        return Some(track);
+    }
+
+    // Fuzzy search
+    let matcher = SkimMatcherV2::default();
+    let mut matches: Vec<_> = tracks
+        .iter()
+        .filter_map(|track| {
+            let score = match_score(&matcher, track, query);
+            if score > 0 {
+                Some((track, score))
             } else {
-                unavailable += 1;
-                let reason = if !file_exists {
-                    "file missing"
-                } else {
-                    "URL unavailable"
-                };
-                println!("  [!] {} - {}", track.display_name(), reason);
+                None
             }
+        })
+        .collect();
+
+    matches.sort_by_key(|m| std::cmp::Reverse(m.1));
+
+    matches.first().map(|(track, _)| *track)
+}
+
+fn match_exact<'a>(tracks: &'a [Track], query: &str) -> Option<&'a Track> {
+    tracks.iter().find(|track| {
+        track.alias.as_deref() == Some(query)
+            || track.title.to_lowercase() == query.to_lowercase()
+            || track.display_title().to_lowercase() == query.to_lowercase()
+    })
+}
+
+/// Like `match_track`, but chapters match too: the track best matching a
+/// query, and the chapter if that matched better than the track itself.
+fn match_track_or_chapter<'a>(
+    tracks: &'a [Track],
+    query: &str,
+) -> Option<(&'a Track, Option<usize>)> {
+    if let Some(track) = match_exact(tracks, query) {
+        return Some((track, None));
+    }
+
+    let matcher = SkimMatcherV2::default();
+    tracks
+        .iter()
+        .filter_map(|track| {
+            let (chapter, score) = match_score_with_chapters(&matcher, track, query)?;
+            Some((track, chapter, score))
+        })
+        .min_by_key(|(_, _, score)| std::cmp::Reverse(*score))
+        .map(|(track, chapter, _)| (track, chapter))
+}
+
+/// How well a track or one of its chapters matches a query, with the
+/// chapter if it matched better; `None` if neither does.
+fn match_score_with_chapters(
+    matcher: &SkimMatcherV2,
+    track: &Track,
+    query: &str,
+) -> Option<(Option<usize>, i64)> {
+    let score = match_score(matcher, track, query);
+    match match_chapter(track, query) {
+        Some((chapter, chapter_score)) if chapter_score > score => {
+            Some((Some(chapter), chapter_score))
         }
+        _ if score > 0 => Some((None, score)),
+        _ => None,
+    }
+}
+
+/// The chapter of a track best matching a query, and how well it does. An
+/// exact title beats any fuzzy match.
+fn match_chapter(track: &Track, query: &str) -> Option<(usize, i64)> {
+    let query_lower = query.to_lowercase();
+    if let Some(i) = track
+        .chapters
+        .iter()
+        .position(|c| c.title.to_lowercase() == query_lower)
+    {
+        return Some((i, i64::MAX));
+    }
 
-        println!("\nAvailable: {available}, Unavailable: {unavailable}");
+    let matcher = SkimMatcherV2::default();
+    track
+        .chapters
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| Some((i, matcher.fuzzy_match(&c.title, query)?)))
+        .min_by_key(|(_, score)| std::cmp::Reverse(*score))
+}
 
-        Ok(())
+/// How well a track's titles, alias and artist fuzzy match a query; 0 if
+/// they don't.
+fn match_score(matcher: &SkimMatcherV2, track: &Track, query: &str) -> i64 {
+    [
+        Some(track.title.as_str()),
+        track.custom_title.as_deref(),
+        track.alias.as_deref(),
+        track.artist.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|text| matcher.fuzzy_match(text, query))
+    .max()
+    .unwrap_or(0)
+}
+
+/// Ask a yes/no question on the terminal. Anything but yes is a no.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Parse an age like `30m`, `12h`, `7d` or `2w`. Plain numbers are days.
+fn parse_age(s: &str) -> Result<chrono::Duration> {
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, unit @ ('m' | 'h' | 'd' | 'w'))) => (&s[..i], unit),
+        _ => (s, 'd'),
+    };
+    let invalid = || format!("Invalid age '{s}'. Use a value like 12h, 7d or 2w");
+    let n: i64 = number.parse().with_context(invalid)?;
+    if n < 0 {
+        bail!(invalid());
+    }
+
+    match unit {
+        'm' => chrono::Duration::try_minutes(n),
+        'h' => chrono::Duration::try_hours(n),
+        'w' => chrono::Duration::try_weeks(n),
+        _ => chrono::Duration::try_days(n),
     }
+    .with_context(invalid)
 }
 
 fn parse_time(s: &str) -> Result<u64> {
@@ -466,7 +2331,10 @@ fn parse_time(s: &str) -> Result<u64> {
         if parts.len() == 2 {
             let mins: u64 = parts[0].parse().context("Invalid minutes")?;
             let secs: u64 = parts[1].parse().context("Invalid seconds")?;
-            return Ok(mins * 60 + secs);
+            return mins
+                .checked_mul(60)
+                .and_then(|mins| mins.checked_add(secs))
+                .with_context(|| format!("Time '{s}' is too long"));
         }
     }
 
@@ -474,6 +2342,207 @@ fn parse_time(s: &str) -> Result<u64> {
         .context("Invalid time format. Use seconds or MM:SS")
 }
 
+/// Parse a time that may have a fraction of a second, like 90.5 or 1:02.25.
+fn parse_precise_time(s: &str) -> Result<std::time::Duration> {
+    let (whole, fraction) = match s.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (s, None),
+    };
+    let time = std::time::Duration::from_secs(parse_time(whole)?);
+    let Some(fraction) = fraction else {
+        return Ok(time);
+    };
+
+    let seconds: f64 = format!("0.{fraction}")
+        .parse()
+        .ok()
+        .filter(|_| fraction.chars().all(|c| c.is_ascii_digit()))
+        .with_context(|| format!("Invalid time '{s}'. Use seconds or MM:SS, like 1:02.5"))?;
+    time.checked_add(std::time::Duration::from_secs_f64(seconds))
+        .with_context(|| format!("Time '{s}' is too long"))
+}
+
+/// Format a time as M:SS.s, keeping tenths of a second.
+fn format_precise(time: std::time::Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+fn format_loop(region: LoopRegion) -> String {
+    format!(
+        "{} - {}",
+        format_precise(region.start),
+        format_precise(region.end)
+    )
+}
+
+enum BatchUpdate {
+    Started(usize, String),
+    Progress(usize, DownloadPhase),
+    Done(usize, BatchOutcome),
+}
+
+enum BatchOutcome {
+    Added(Box<Track>),
+    Skipped(Box<Track>),
+    /// Another entry resolved to the same canonical URL
+    Duplicate(String),
+    Failed(anyhow::Error),
+}
+
+fn report_duplicates(count: usize) {
+    if count > 0 {
+        println!("Skipping {count} duplicate URLs in the batch.");
+    }
+}
+
+/// Download one batch entry on a worker thread, reporting progress to the caller.
+///
+/// `claimed` holds the canonical URLs other workers are handling, so two
+/// entries for the same video aren't downloaded at once.
+fn download_batch_entry(
+    downloader: &Downloader,
+    db_path: &Path,
+    claimed: &Mutex<HashSet<String>>,
+    index: usize,
+    entry: &BatchEntry,
+    tx: &Sender<BatchUpdate>,
+) -> BatchOutcome {
+    if is_collection_url(&entry.url) {
+        return BatchOutcome::Failed(anyhow!(
+            "Playlist and channel URLs must be added on their own"
+        ));
+    }
+
+    let (title, canonical_url, _) = match downloader.get_video_info(&entry.url) {
+        Ok(info) => info,
+        Err(e) => return BatchOutcome::Failed(e.into()),
+    };
+
+    if !claimed.lock().unwrap().insert(canonical_url.clone()) {
+        return BatchOutcome::Duplicate(canonical_url);
+    }
+
+    // Open a separate DB connection for the duplicate check
+    let existing = Database::open(db_path).and_then(|db| db.get_track_by_source(&canonical_url));
+    match existing {
+        Ok(Some(existing)) => return BatchOutcome::Skipped(Box::new(existing)),
+        Ok(None) => {}
+        Err(e) => return BatchOutcome::Failed(e.context("Failed to check the library")),
+    }
+
+    let _ = tx.send(BatchUpdate::Started(index, title));
+    let start = extract_start_time(&entry.url);
+    match downloader.download(&canonical_url, start, |phase| {
+        let _ = tx.send(BatchUpdate::Progress(index, phase));
+    }) {
+        Ok(track) => BatchOutcome::Added(Box::new(track)),
+        Err(e) => BatchOutcome::Failed(e.into()),
+    }
+}
+
+/// Move a directory, copying when a rename isn't possible (e.g. across
+/// filesystems). The source is only removed once the copy is complete.
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if to.exists() {
+        fs::remove_dir(to)?;
+    }
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if let Err(e) = copy_dir(from, to) {
+        let _ = fs::remove_dir_all(to);
+        return Err(e.context(format!("Failed to copy library to {}", to.display())));
+    }
+
+    fs::remove_dir_all(from)
+        .with_context(|| format!("Library copied, but failed to remove {}", from.display()))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The track's format, if the player can't decode it.
+fn unplayable_format(track: &Track) -> Option<String> {
+    track.format.clone().filter(|f| !audio::can_play(f))
+}
+
+fn warn_unplayable(format: &str) {
+    eprintln!(
+        "Warning: the player can't decode {format} files. Supported formats: {}",
+        audio::PLAYABLE_FORMATS.join(", ")
+    );
+}
+
+/// Turn a download error into a user-facing one, with a hint where there's
+/// something the user can do about it.
+fn explain(e: DownloadError) -> anyhow::Error {
+    match e.hint() {
+        Some(hint) => anyhow!("{e}\n{hint}"),
+        None => e.into(),
+    }
+}
+
+fn print_download_progress(phase: DownloadPhase) {
+    match phase {
+        DownloadPhase::Downloading {
+            percent,
+            speed,
+            eta,
+        } => {
+            eprint!("\r  [{:5.1}%] {} ETA {}    ", percent, speed, eta);
+        }
+        DownloadPhase::Converting => {
+            eprint!("\r  Converting audio...          \n");
+        }
+        DownloadPhase::Retrying {
+            attempt,
+            max_attempts,
+            delay_secs,
+            reason,
+        } => {
+            eprint!(
+                "\r  Attempt {attempt}/{max_attempts} failed: {reason}\n  Retrying in {delay_secs}s...\n"
+            );
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
 fn format_duration(seconds: u64) -> String {
     let mins = seconds / 60;
     let secs = seconds % 60;
@@ -491,11 +2560,136 @@ fn print_status(status: &PlaybackState) {
         println!(
             "Duration: {} / {}",
             format_duration(status.position),
-            track.format_duration()
+            format_duration(track.play_end())
         );
+        print_chapter(status);
+        if let Some(region) = status.loop_region {
+            println!("Loop: {}", format_loop(region));
+        }
     } else {
         println!("Not playing");
     }
 
     println!("Volume: {}%", status.volume);
 }
+
+fn expect_ok(response: DaemonResponse) -> Result<()> {
+    match response {
+        DaemonResponse::Error(e) => bail!("{e}"),
+        _ => Ok(()),
+    }
+}
+
+fn print_chapter(status: &PlaybackState) {
+    if let (Some(track), Some(index)) = (&status.current_track, status.chapter) {
+        println!(
+            "Chapter {}/{}: {}",
+            index + 1,
+            track.chapters.len(),
+            track.chapters[index].title
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Chapter;
+
+    fn chaptered(title: &str, chapters: &[&str]) -> Track {
+        let mut track = Track::new(title.to_string(), title.to_string(), 600, String::new());
+        track.chapters = chapters
+            .iter()
+            .zip(0..)
+            .map(|(title, i)| Chapter {
+                title: title.to_string(),
+                start: i * 100,
+                end: (i + 1) * 100,
+            })
+            .collect();
+        track
+    }
+
+    #[test]
+    fn test_match_chapter() {
+        let track = chaptered("Mix", &["Intro", "Deep Focus", "Deep"]);
+
+        // An exact title wins, whatever the case
+        assert_eq!(match_chapter(&track, "deep"), Some((2, i64::MAX)));
+        assert_eq!(match_chapter(&track, "fcs").unwrap().0, 1);
+        assert_eq!(match_chapter(&track, "intr").unwrap().0, 0);
+        assert_eq!(match_chapter(&track, "xyz"), None);
+        assert_eq!(match_chapter(&chaptered("Plain", &[]), "intro"), None);
+    }
+
+    #[test]
+    fn test_match_track_or_chapter() {
+        let tracks = vec![
+            chaptered("Lofi Mix", &["Rainy Night", "Morning Coffee"]),
+            chaptered("Jazz Evening", &["Opening", "Lofi Interlude"]),
+        ];
+        let found = |query| {
+            let (track, chapter) = match_track_or_chapter(&tracks, query)?;
+            Some((track.title.as_str(), chapter))
+        };
+
+        // Track titles match the track itself
+        assert_eq!(found("jazz evening"), Some(("Jazz Evening", None)));
+        assert_eq!(found("lofimix"), Some(("Lofi Mix", None)));
+        // Chapters matching better than any track pick the chapter
+        assert_eq!(found("morning coffee"), Some(("Lofi Mix", Some(1))));
+        assert_eq!(found("Lofi Interlude"), Some(("Jazz Evening", Some(1))));
+        assert_eq!(found("zzz"), None);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("90").unwrap(), 90);
+        assert_eq!(parse_time("1:30").unwrap(), 90);
+        assert_eq!(parse_time("0:05").unwrap(), 5);
+
+        assert!(parse_time("1:30:00").is_err());
+        assert!(parse_time("-5").is_err());
+        assert!(parse_time("307445734561825861:00").is_err());
+        assert!(parse_time("307445734561825860:99").is_err());
+    }
+
+    #[test]
+    fn test_parse_precise_time() {
+        let ms = std::time::Duration::from_millis;
+        assert_eq!(parse_precise_time("1:02.25").unwrap(), ms(62_250));
+        assert_eq!(parse_precise_time("90.5").unwrap(), ms(90_500));
+        assert_eq!(parse_precise_time("90").unwrap(), ms(90_000));
+        assert_eq!(parse_precise_time("1.").unwrap(), ms(1_000));
+
+        assert!(parse_precise_time("1.2.3").is_err());
+        assert!(parse_precise_time("1.-5").is_err());
+        assert!(parse_precise_time("1.5e3").is_err());
+        assert!(parse_precise_time(".5").is_err());
+        assert!(parse_precise_time("18446744073709551615.9999999999").is_err());
+    }
+
+    #[test]
+    fn test_format_precise() {
+        let ms = std::time::Duration::from_millis;
+        assert_eq!(format_precise(ms(0)), "0:00.0");
+        assert_eq!(format_precise(ms(62_250)), "1:02.2");
+        assert_eq!(format_precise(ms(90_500)), "1:30.5");
+        assert_eq!(format_precise(ms(3_600_000)), "60:00.0");
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30m").unwrap(), chrono::Duration::minutes(30));
+        assert_eq!(parse_age("12h").unwrap(), chrono::Duration::hours(12));
+        assert_eq!(parse_age("7").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_age("2w").unwrap(), chrono::Duration::weeks(2));
+
+        assert!(parse_age("").is_err());
+        assert!(parse_age("-3d").is_err());
+        assert!(parse_age("3y").is_err());
+        // Too long for chrono
+        assert!(parse_age("99999999999999w").is_err());
+        assert!(parse_age("99999999999999999d").is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use anyhow::{Result, bail};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::audio::AudioPlayer;
+use crate::config::Config;
+use crate::db::{Database, SCHEMA_VERSION};
+use crate::ipc::DaemonClient;
+
+/// yt-dlp releases older than this tend to break on YouTube changes.
+const YT_DLP_MAX_AGE_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The outcome of one `mixyt doctor` check.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub message: String,
+    /// What to do about a warning or error
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warning,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn error(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Error,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Diagnose the environment. Runs without `App`, so it still works when the
+/// config or database can't be loaded.
+pub fn run_doctor(json: bool) -> Result<()> {
+    let (config_check, config) = check_config();
+    let mut checks = vec![config_check];
+    checks.extend(check_directories(&config));
+    checks.push(check_yt_dlp(&config));
+    checks.push(check_ffmpeg("ffmpeg", Status::Error));
+    checks.push(check_ffmpeg("ffprobe", Status::Warning));
+    checks.push(check_database(&config));
+    checks.push(check_daemon(&config));
+    checks.push(check_audio_output());
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        for check in &checks {
+            let label = match check.status {
+                Status::Ok => "[ok]  ",
+                Status::Warning => "[warn]",
+                Status::Error => "[fail]",
+            };
+            println!("{label} {}: {}", check.name, check.message);
+            if let Some(ref fix) = check.fix {
+                println!("       Fix: {fix}");
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Error).count();
+    if failed > 0 {
+        bail!("{failed} checks failed");
+    }
+
+    Ok(())
+}
+
+/// Check the config parses and its values are valid, falling back to the
+/// defaults for the other checks if it doesn't.
+fn check_config() -> (Check, Config) {
+    let path = Config::config_path();
+    if !path.exists() {
+        return (
+            Check::ok(
+                "config",
+                format!("{} not found, using defaults", path.display()),
+            ),
+            Config::default(),
+        );
+    }
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            return (
+                Check::error(
+                    "config",
+                    format!("{e:#}"),
+                    format!("Fix or remove {}", path.display()),
+                ),
+                Config::default(),
+            );
+        }
+    };
+
+    let invalid = config
+        .audio
+        .yt_dlp_quality()
+        .err()
+        .or_else(|| config.storage.max_size_bytes().err());
+    let check = match invalid {
+        Some(e) => Check::error("config", e.to_string(), format!("Edit {}", path.display())),
+        None => Check::ok("config", path.display().to_string()),
+    };
+
+    (check, config)
+}
+
+fn check_directories(config: &Config) -> Vec<Check> {
+    [
+        ("data directory", config.data_dir().clone()),
+        ("audio directory", config.audio_dir()),
+    ]
+    .into_iter()
+    .map(|(name, dir)| {
+        if !dir.exists() {
+            return Check::ok(name, format!("{} (created on first use)", dir.display()));
+        }
+
+        let probe = dir.join(".mixyt-doctor");
+        match fs::write(&probe, b"") {
+            Ok(()) => {
+                let _ = fs::remove_file(&probe);
+                Check::ok(name, dir.display().to_string())
+            }
+            Err(e) => Check::error(
+                name,
+                format!("{} is not writable: {e}", dir.display()),
+                format!("Fix the permissions of {}", dir.display()),
+            ),
+        }
+    })
+    .collect()
+}
+
+/// First line of `<program> <arg>`'s output, or `None` if it can't be run.
+fn version_output(program: &str, arg: &str) -> Option<String> {
+    let output = Command::new(program).arg(arg).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+fn check_yt_dlp(config: &Config) -> Check {
+    let program = &config.download.yt_dlp;
+    let Some(version) = version_output(program, "--version") else {
+        return Check::error(
+            "yt-dlp",
+            format!("{program} not found"),
+            "Install it from https://github.com/yt-dlp/yt-dlp",
+        );
+    };
+
+    match yt_dlp_age(&version, Utc::now().date_naive()) {
+        Some(age) => {
+            if age > YT_DLP_MAX_AGE_DAYS {
+                Check::warning(
+                    "yt-dlp",
+                    format!(
+                        "{version} is {age} days old; YouTube changes often break old releases"
+                    ),
+                    format!("Update it with: {program} -U (or your package manager)"),
+                )
+            } else {
+                Check::ok("yt-dlp", version)
+            }
+        }
+        None => Check::ok("yt-dlp", version),
+    }
+}
+
+/// Days since a yt-dlp version was released on `today`. Versions are
+/// release dates, e.g. 2024.08.06, or 2024.08.06.232 for nightlies.
+fn yt_dlp_age(version: &str, today: NaiveDate) -> Option<i64> {
+    let date = version.get(..10)?;
+    let released = NaiveDate::parse_from_str(date, "%Y.%m.%d").ok()?;
+    Some((today - released).num_days())
+}
+
+/// ffmpeg is needed to extract and convert audio; ffprobe only to record
+/// bitrates, so its absence is less severe.
+fn check_ffmpeg(program: &'static str, missing: Status) -> Check {
+    match version_output(program, "-version") {
+        Some(line) => {
+            let version = line
+                .strip_prefix(&format!("{program} version "))
+                .and_then(|rest| rest.split_whitespace().next())
+                .unwrap_or(&line);
+            Check::ok(program, version.to_string())
+        }
+        None => Check {
+            name: program,
+            status: missing,
+            message: format!("{program} not found"),
+            fix: Some("Install ffmpeg from https://ffmpeg.org or your package manager".to_string()),
+        },
+    }
+}
+
+fn check_database(config: &Config) -> Check {
+    let path = config.db_path();
+    if !path.exists() {
+        return Check::ok(
+            "database",
+            format!("{} (created on first use)", path.display()),
+        );
+    }
+
+    // Only look: opening it normally would migrate it
+    let fix = "Restore the database from a backup, or re-import a library export";
+    let checked = Database::open_read_only(&path)
+        .and_then(|db| Ok((db.schema_version()?, db.integrity_check()?)));
+    match checked {
+        Err(e) => Check::error("database", format!("{e:#}"), fix),
+        Ok((_, problems)) if !problems.is_empty() => Check::error(
+            "database",
+            format!("Integrity check failed: {}", problems.join("; ")),
+            fix,
+        ),
+        Ok((version, _)) if version > SCHEMA_VERSION => Check::error(
+            "database",
+            format!(
+                "Schema version {version} is newer than this mixyt supports ({SCHEMA_VERSION})"
+            ),
+            "Update mixyt",
+        ),
+        Ok((version, _)) if version < SCHEMA_VERSION => Check::warning(
+            "database",
+            format!("Schema version {version}, this mixyt uses {SCHEMA_VERSION}"),
+            "It's upgraded the next time mixyt uses it; back it up first to keep using an older mixyt",
+        ),
+        Ok(_) => Check::ok("database", path.display().to_string()),
+    }
+}
+
+/// The socket is what the CLI talks to; the PID file is only bookkeeping, so
+/// either one existing without a daemon behind it is stale.
+fn check_daemon(config: &Config) -> Check {
+    let socket = config.socket_path();
+    let pid_file = config.pid_path();
+    let pid = fs::read_to_string(&pid_file).ok();
+
+    if DaemonClient::new(&socket).is_daemon_running() {
+        let pid = pid.as_deref().map(str::trim).unwrap_or("unknown");
+        return Check::ok("daemon", format!("Running (pid {pid})"));
+    }
+
+    let stale: Vec<&Path> = [socket.as_path(), pid_file.as_path()]
+        .into_iter()
+        .filter(|p| p.exists())
+        .collect();
+    if stale.is_empty() {
+        let note = if config.daemon.auto_start {
+            "started automatically when needed"
+        } else {
+            "start it with: mixyt daemon start"
+        };
+        return Check::ok("daemon", format!("Not running ({note})"));
+    }
+
+    let files: Vec<String> = stale.iter().map(|p| p.display().to_string()).collect();
+    Check::warning(
+        "daemon",
+        format!("Not running, but left stale files: {}", files.join(", ")),
+        format!(
+            "Remove them with: rm {}; then run: mixyt daemon start",
+            files.join(" ")
+        ),
+    )
+}
+
+fn check_audio_output() -> Check {
+    match AudioPlayer::new() {
+        Ok(_) => Check::ok("audio output", "Default output device available"),
+        Err(e) => Check::error(
+            "audio output",
+            format!("No output device: {}", e.root_cause()),
+            "Check that an output device is connected and a sound server (PipeWire, PulseAudio) is running",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(dir: &Path) -> Config {
+        let mut config = Config::default();
+        config.storage.path = dir.to_path_buf();
+        config
+    }
+
+    #[test]
+    fn test_yt_dlp_age() {
+        let today = NaiveDate::from_ymd_opt(2024, 9, 1).unwrap();
+        assert_eq!(yt_dlp_age("2024.08.06", today), Some(26));
+        assert_eq!(yt_dlp_age("2024.08.06.232", today), Some(26));
+        assert_eq!(yt_dlp_age("2023.09.01", today), Some(366));
+        assert_eq!(yt_dlp_age("unknown", today), None);
+        assert_eq!(yt_dlp_age("", today), None);
+    }
+
+    #[test]
+    fn test_check_daemon_stale_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        let check = check_daemon(&config);
+        assert_eq!(check.status, Status::Ok);
+        assert!(check.message.starts_with("Not running"));
+
+        // Left behind by a daemon that was killed
+        fs::write(config.pid_path(), "12345").unwrap();
+        fs::write(config.socket_path(), "").unwrap();
+        let check = check_daemon(&config);
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.message.contains("mixyt.sock"));
+        assert!(check.message.contains("mixyt.pid"));
+    }
+
+    #[test]
+    fn test_check_database_reports_without_migrating() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        let path = config.db_path();
+
+        assert_eq!(check_database(&config).status, Status::Ok);
+        assert!(!path.exists());
+
+        Database::open(&path).unwrap();
+        assert_eq!(check_database(&config).status, Status::Ok);
+
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("PRAGMA user_version = 3")
+            .unwrap();
+        assert_eq!(check_database(&config).status, Status::Warning);
+        let version = Database::open_read_only(&path)
+            .unwrap()
+            .schema_version()
+            .unwrap();
+        assert_eq!(version, 3);
+    }
+}
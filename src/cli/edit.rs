@@ -0,0 +1,126 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::models::Track;
+
+/// The fields of a track `mixyt edit` changes. An empty string clears a
+/// field, and an empty title goes back to the fetched one.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackEdit {
+    pub title: String,
+    pub alias: String,
+    pub artist: String,
+    pub notes: String,
+}
+
+impl TrackEdit {
+    pub fn from_track(track: &Track) -> Self {
+        Self {
+            title: track.display_title().to_string(),
+            alias: track.alias.clone().unwrap_or_default(),
+            artist: track.artist.clone().unwrap_or_default(),
+            notes: track.notes.clone().unwrap_or_default(),
+        }
+    }
+
+    /// The track with these fields applied. The fetched title is kept, so a
+    /// title equal to it is no override.
+    pub fn apply(&self, track: &Track) -> Track {
+        let field = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+        Track {
+            custom_title: field(&self.title).filter(|t| *t != track.title),
+            alias: field(&self.alias),
+            artist: field(&self.artist),
+            notes: field(&self.notes),
+            ..track.clone()
+        }
+    }
+}
+
+/// Open a track's fields as TOML in `$VISUAL` or `$EDITOR`, returning what
+/// they were edited to.
+pub fn edit_in_editor(track: &Track) -> Result<TrackEdit> {
+    let path = env::temp_dir().join(format!("mixyt-edit-{}.toml", track.id));
+    let content = format!(
+        "# Editing: {}\n# Fetched title: {}\n# URL: {}\n\
+         # Empty fields are cleared; an empty title restores the fetched one.\n\n{}",
+        track.display_name(),
+        track.title,
+        track.url,
+        toml::to_string(&TrackEdit::from_track(track))?
+    );
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let edited = run_editor(&path).and_then(|_| {
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content).with_context(|| {
+            format!(
+                "Invalid TOML, nothing was changed. Your edits are in {}",
+                path.display()
+            )
+        })
+    })?;
+
+    let _ = fs::remove_file(&path);
+    Ok(edited)
+}
+
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // The editor may come with arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let Some(program) = parts.next() else {
+        bail!("$EDITOR is empty");
+    };
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run editor '{program}'"))?;
+    if !status.success() {
+        bail!("{program} exited with {status}, nothing was changed");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_edit() {
+        let track = Track::new(
+            "https://example.com/mix".to_string(),
+            "Fetched Title".to_string(),
+            60,
+            "mix.mp3".to_string(),
+        );
+
+        let mut edit = TrackEdit::from_track(&track);
+        edit.title = "Better Title".to_string();
+        edit.artist = " Someone ".to_string();
+        let edited = edit.apply(&track);
+        assert_eq!(edited.display_title(), "Better Title");
+        assert_eq!(edited.title, "Fetched Title");
+        assert_eq!(edited.artist.as_deref(), Some("Someone"));
+        assert_eq!(edited.alias, None);
+
+        // Clearing the title, or setting the fetched one, drops the override
+        edit.title.clear();
+        assert_eq!(edit.apply(&edited).custom_title, None);
+        edit.title = "Fetched Title".to_string();
+        assert_eq!(edit.apply(&edited).custom_title, None);
+
+        let parsed: TrackEdit = toml::from_str(&toml::to_string(&edit).unwrap()).unwrap();
+        assert_eq!(parsed, edit);
+    }
+}
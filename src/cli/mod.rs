@@ -1,7 +1,11 @@
 use clap::{Parser, Subcommand};
 
+mod batch;
 mod commands;
+mod doctor;
+mod edit;
 pub use commands::*;
+pub use doctor::run_doctor;
 
 #[derive(Parser)]
 #[command(name = "mixyt")]
@@ -14,13 +18,35 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Add a track to the library from a YouTube URL
+    /// Add a track, playlist, or channel to the library from a YouTube URL
     Add {
-        /// YouTube URL
-        url: String,
+        /// YouTube video, playlist, or channel URL ("-" reads a batch from stdin)
+        #[arg(required_unless_present = "file")]
+        url: Option<String>,
         /// Optional alias for quick reference
         #[arg(short, long)]
         alias: Option<String>,
+        /// Create or update a mixyt playlist named after the imported playlist
+        #[arg(long)]
+        save_playlist: bool,
+        /// Add URLs from a file, one "<url> [alias] [playlist]" per line
+        #[arg(short, long, conflicts_with_all = ["url", "alias", "save_playlist"])]
+        file: Option<String>,
+        /// Number of parallel downloads for batch adds
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Queue the download in the daemon and return immediately
+        #[arg(short, long, conflicts_with_all = ["save_playlist", "jobs"])]
+        background: bool,
+        /// Audio format to convert to, e.g. mp3, opus, flac (default: audio.format)
+        #[arg(long, conflicts_with = "background")]
+        format: Option<String>,
+        /// Audio quality: best, high, medium, low, 0-10 or a bitrate like 128k
+        #[arg(long, conflicts_with = "background")]
+        quality: Option<String>,
+        /// Keep the original audio stream without re-encoding
+        #[arg(long, conflicts_with_all = ["format", "quality", "background"])]
+        keep_original: bool,
     },
 
     /// Remove a track from the library
@@ -29,10 +55,56 @@ pub enum Commands {
         query: String,
     },
 
-    /// Play a track
-    Play {
+    /// Edit a track's title, alias, artist or notes; opens $EDITOR without options
+    Edit {
         /// Track name, alias, or search query
         query: String,
+        /// Title to show instead of the fetched one ("" restores it)
+        #[arg(long)]
+        title: Option<String>,
+        /// Alias ("" clears it)
+        #[arg(long)]
+        alias: Option<String>,
+        #[arg(long)]
+        artist: Option<String>,
+        #[arg(long)]
+        notes: Option<String>,
+    },
+
+    /// Skip a track's intro or outro; shows the current trim without options
+    Trim {
+        /// Track name, alias, or search query
+        query: String,
+        /// Where playback starts (e.g., "0:32" or "32")
+        #[arg(long)]
+        start: Option<String>,
+        /// Where playback ends (e.g., "58:10")
+        #[arg(long)]
+        end: Option<String>,
+        /// Play the whole track again
+        #[arg(long, conflicts_with_all = ["start", "end"])]
+        clear: bool,
+    },
+
+    /// Give tracks extra short names
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommands,
+    },
+
+    /// List, restore or empty removed tracks
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+
+    /// Play a track, or stream a URL while it downloads
+    Play {
+        /// Track name, alias, search query, or URL
+        query: String,
+        /// Add a streamed URL to the library once it has downloaded
+        #[arg(long)]
+        save: bool,
     },
 
     /// Pause playback
@@ -50,6 +122,60 @@ pub enum Commands {
         position: String,
     },
 
+    /// Skip to the next chapter of the current track
+    NextChapter,
+
+    /// Go back to the start of the chapter, or to the previous one
+    PrevChapter,
+
+    /// Jump to a chapter of the current track
+    Chapter {
+        /// Chapter number, or title to search for
+        chapter: String,
+    },
+
+    /// List the chapters of a track
+    Chapters {
+        /// Track name, alias, or search query (default: the current track)
+        query: Option<String>,
+        /// Fetch the chapters from the source again
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Bookmark a position in the playing track
+    Mark {
+        /// Name of the mark; an existing mark with it is moved
+        name: String,
+        /// "at <time>" to mark a time other than the current position
+        #[arg(num_args = 0..=2, value_names = ["at", "time"])]
+        at: Vec<String>,
+        /// Remove the mark instead
+        #[arg(long, conflicts_with = "at")]
+        delete: bool,
+    },
+
+    /// List bookmarks
+    Marks {
+        /// Track name, alias, or search query (default: the current track, or
+        /// all marked tracks)
+        query: Option<String>,
+    },
+
+    /// Seek to a bookmark
+    Goto {
+        /// Name of the mark
+        name: String,
+    },
+
+    /// Loop a section of the playing track, or "off" to stop looping
+    Loop {
+        /// Start of the loop: a time like 1:02.5, or a mark
+        a: Option<String>,
+        /// End of the loop: a time like 1:10, or a mark
+        b: Option<String>,
+    },
+
     /// Set or show volume
     Volume {
         /// Volume level (0-100)
@@ -74,6 +200,68 @@ pub enum Commands {
         command: DaemonCommands,
     },
 
+    /// Re-encode tracks to another format with ffmpeg
+    Convert {
+        /// Track name, alias, or search query
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        query: Option<String>,
+        /// Convert every track in the library
+        #[arg(long)]
+        all: bool,
+        /// Target format: mp3, opus, vorbis, aac, flac or wav (default: audio.format)
+        #[arg(long)]
+        format: Option<String>,
+        /// Target bitrate, e.g. 128k (default: the encoder's default)
+        #[arg(long)]
+        bitrate: Option<String>,
+        /// Number of files to convert at once
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Convert playable files to a format the player can't decode
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Show disk usage of the library
+    Du {
+        /// Number of tracks and playlists to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Clean up leftover files and broken library entries
+    Gc {
+        /// Only report what would be cleaned up
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't ask for confirmation
+        #[arg(short, long, conflicts_with = "dry_run")]
+        yes: bool,
+    },
+
+    /// Merge tracks that are the same video added under different URLs
+    Dedupe {
+        /// Only report the duplicates
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't ask for confirmation
+        #[arg(short, long, conflicts_with = "dry_run")]
+        yes: bool,
+    },
+
+    /// Diagnose problems with the environment and library
+    Doctor {
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Manage where the library is stored
+    Library {
+        #[command(subcommand)]
+        command: LibraryCommands,
+    },
+
     /// Export library to JSON
     Export {
         /// Output file path
@@ -88,7 +276,47 @@ pub enum Commands {
     },
 
     /// Check track availability
-    Check,
+    Check {
+        /// Only re-check tracks last checked longer ago than this, e.g. 7d or 12h
+        #[arg(long)]
+        stale: Option<String>,
+        /// Only check that audio files exist, without going online
+        #[arg(long, conflicts_with_all = ["stale", "jobs"])]
+        offline: bool,
+        /// Number of tracks to check at once
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Follow a YouTube channel or playlist and download its new uploads on sync
+    Subscribe {
+        /// YouTube channel or playlist URL
+        url: String,
+        /// Playlist to add new tracks to (created if missing)
+        #[arg(long)]
+        into: Option<String>,
+        /// Only download matching entries (e.g., "dur:>20m title:live")
+        #[arg(long)]
+        filter: Option<String>,
+    },
+
+    /// Stop following a channel or playlist
+    Unsubscribe {
+        /// Subscription URL or title
+        query: String,
+    },
+
+    /// List subscriptions
+    Subscriptions,
+
+    /// Download new entries from all subscriptions
+    Sync,
+
+    /// Manage the daemon's background downloads
+    Downloads {
+        #[command(subcommand)]
+        command: Option<DownloadCommands>,
+    },
 
     /// Launch interactive TUI
     #[command(name = "tui")]
@@ -106,3 +334,69 @@ pub enum DaemonCommands {
     /// Run daemon in foreground (internal use)
     Run,
 }
+
+#[derive(Subcommand)]
+pub enum LibraryCommands {
+    /// Move the library (database and audio files) to a new directory
+    Move {
+        /// New library directory (must not exist, or be empty)
+        path: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AliasCommands {
+    /// Add aliases to a track; each alias belongs to one track only
+    Add {
+        /// Track name, alias, or search query
+        query: String,
+        /// Aliases to add
+        #[arg(required = true)]
+        aliases: Vec<String>,
+    },
+    /// Remove aliases
+    Rm {
+        #[arg(required = true)]
+        aliases: Vec<String>,
+    },
+    /// List a track's aliases, or all aliases
+    List {
+        /// Track name, alias, or search query
+        query: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    /// List removed tracks
+    List,
+    /// Put a removed track back in the library and its playlists
+    Restore {
+        /// Track name or search query (default: the last removed track)
+        query: Option<String>,
+    },
+    /// Permanently delete all removed tracks
+    Empty {
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DownloadCommands {
+    /// List queued, running and finished downloads
+    List,
+    /// Cancel a queued or running download
+    Cancel {
+        /// Download ID (or a unique prefix)
+        id: String,
+    },
+    /// Retry a failed or cancelled download
+    Retry {
+        /// Download ID (or a unique prefix)
+        id: String,
+    },
+    /// Remove finished downloads from the list
+    Clear,
+}
@@ -1,7 +1,9 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
@@ -10,6 +12,8 @@ pub struct Config {
     #[serde(default)]
     pub audio: AudioConfig,
     #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
     pub daemon: DaemonConfig,
     #[serde(default)]
     pub playback: PlaybackConfig,
@@ -18,6 +22,17 @@ pub struct Config {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub path: PathBuf,
+    /// Quota for downloaded audio, e.g. "20GB". Least-recently-played tracks
+    /// are evicted to stay under it.
+    #[serde(default)]
+    pub max_size: Option<String>,
+    /// Days removed tracks stay in the trash (0 keeps them until emptied)
+    #[serde(default = "default_trash_days")]
+    pub trash_days: u64,
+}
+
+fn default_trash_days() -> u64 {
+    30
 }
 
 impl Default for StorageConfig {
@@ -25,16 +40,76 @@ impl Default for StorageConfig {
         let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
         Self {
             path: home.join(".mixyt"),
+            max_size: None,
+            trash_days: default_trash_days(),
         }
     }
 }
 
+impl StorageConfig {
+    /// The quota in bytes, if one is set.
+    pub fn max_size_bytes(&self) -> Result<Option<u64>> {
+        self.max_size.as_deref().map(parse_size).transpose()
+    }
+}
+
+/// Parse a size like "500MB", "1.5G" or "1024" (bytes). Units are binary.
+fn parse_size(s: &str) -> Result<u64> {
+    let lower = s.trim().to_lowercase();
+    let number = lower.trim_end_matches(['b', 'i']);
+    let (number, multiplier) = match number.char_indices().last() {
+        Some((i, 'k')) => (&number[..i], 1u64 << 10),
+        Some((i, 'm')) => (&number[..i], 1 << 20),
+        Some((i, 'g')) => (&number[..i], 1 << 30),
+        Some((i, 't')) => (&number[..i], 1 << 40),
+        _ => (number, 1),
+    };
+
+    match number.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok((n * multiplier as f64) as u64),
+        _ => bail!("Invalid storage.max_size '{s}'. Use a size like 500MB or 20GB"),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
+    /// Format to convert downloads to, or "original" to keep the source stream
     pub format: String,
+    /// "best", "high", "medium", "low", a VBR level 0-10 or a bitrate like "128k"
     pub quality: String,
 }
 
+impl AudioConfig {
+    pub fn keeps_original(&self) -> bool {
+        self.format == "original"
+    }
+
+    /// The `quality` setting as a yt-dlp `--audio-quality` value.
+    pub fn yt_dlp_quality(&self) -> Result<String> {
+        let quality = self.quality.trim().to_lowercase();
+
+        let value = match quality.as_str() {
+            "best" => "0".to_string(),
+            "high" => "2".to_string(),
+            "medium" => "5".to_string(),
+            "low" => "7".to_string(),
+            q if q.parse::<u8>().is_ok_and(|level| level <= 10) => q.to_string(),
+            q if q
+                .strip_suffix('k')
+                .is_some_and(|kbps| kbps.parse::<u32>().is_ok_and(|n| n > 0)) =>
+            {
+                q.to_uppercase()
+            }
+            _ => bail!(
+                "Invalid audio quality '{}'. Use best, high, medium, low, 0-10 or a bitrate like 128k",
+                self.quality
+            ),
+        };
+
+        Ok(value)
+    }
+}
+
 impl Default for AudioConfig {
     fn default() -> Self {
         Self {
@@ -44,14 +119,74 @@ impl Default for AudioConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// Number of downloads to run at once when adding in batch
+    pub concurrency: usize,
+    pub retries: RetryConfig,
+    /// yt-dlp executable, as a name on `PATH` or a full path
+    pub yt_dlp: String,
+    /// Extractor to use per host, e.g. `"soundcloud.com" = "yt-dlp"`.
+    /// Subdomains match too; unlisted hosts use yt-dlp.
+    pub extractors: BTreeMap<String, String>,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 3,
+            retries: RetryConfig::default(),
+            yt_dlp: "yt-dlp".to_string(),
+            extractors: BTreeMap::new(),
+        }
+    }
+}
+
+/// Retry policy for transient (network-class) download failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total attempts per download, including the first
+    pub attempts: u32,
+    /// Seconds to wait before the first retry; doubles on each retry
+    pub backoff: u64,
+    /// Upper bound on the wait between retries, in seconds
+    pub max_backoff: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: 2,
+            max_backoff: 60,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Wait before retrying after the given (1-based) failed attempt.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_secs(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DaemonConfig {
     pub auto_start: bool,
+    /// Minutes between automatic subscription syncs (0 disables them)
+    #[serde(default)]
+    pub sync_interval: u64,
 }
 
 impl Default for DaemonConfig {
     fn default() -> Self {
-        Self { auto_start: true }
+        Self {
+            auto_start: true,
+            sync_interval: 0,
+        }
     }
 }
 
@@ -91,7 +226,6 @@ impl Config {
         }
     }
 
-    #[allow(dead_code)]
     pub fn save(&self) -> Result<()> {
         let config_dir = Self::config_dir();
         fs::create_dir_all(&config_dir).with_context(|| {
@@ -118,6 +252,31 @@ impl Config {
         self.storage.path.join("audio")
     }
 
+    /// Resolve a track's stored file path. Library files are stored relative
+    /// to the audio directory; absolute paths are returned unchanged.
+    pub fn resolve_audio_path(&self, file_path: &str) -> PathBuf {
+        self.audio_dir().join(file_path)
+    }
+
+    /// The form of `path` to store for a track: relative if it lives in the
+    /// audio directory, absolute otherwise.
+    pub fn relative_audio_path(&self, path: &Path) -> String {
+        path.strip_prefix(self.audio_dir())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Scratch space for audio that is played while it downloads.
+    pub fn stream_dir(&self) -> PathBuf {
+        self.storage.path.join("streams")
+    }
+
+    /// Audio files of removed tracks, until the trash is emptied.
+    pub fn trash_dir(&self) -> PathBuf {
+        self.storage.path.join("trash")
+    }
+
     pub fn db_path(&self) -> PathBuf {
         self.storage.path.join("mixyt.db")
     }
@@ -146,3 +305,78 @@ impl Config {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let retries = RetryConfig {
+            attempts: 5,
+            backoff: 2,
+            max_backoff: 10,
+        };
+        assert_eq!(retries.delay_for(1), Duration::from_secs(2));
+        assert_eq!(retries.delay_for(2), Duration::from_secs(4));
+        assert_eq!(retries.delay_for(3), Duration::from_secs(8));
+        assert_eq!(retries.delay_for(4), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_audio_quality() {
+        let quality = |q: &str| {
+            AudioConfig {
+                format: "mp3".to_string(),
+                quality: q.to_string(),
+            }
+            .yt_dlp_quality()
+        };
+
+        assert_eq!(quality("best").unwrap(), "0");
+        assert_eq!(quality("medium").unwrap(), "5");
+        assert_eq!(quality("3").unwrap(), "3");
+        assert_eq!(quality("128k").unwrap(), "128K");
+        assert!(quality("11").is_err());
+        assert!(quality("loud").is_err());
+    }
+
+    #[test]
+    fn test_audio_paths() {
+        let mut config = Config::default();
+        config.storage.path = PathBuf::from("/music/mixyt");
+
+        let stored = config.relative_audio_path(Path::new("/music/mixyt/audio/Mix [abc].mp3"));
+        assert_eq!(stored, "Mix [abc].mp3");
+        assert_eq!(
+            config.resolve_audio_path(&stored),
+            PathBuf::from("/music/mixyt/audio/Mix [abc].mp3")
+        );
+
+        // Files outside the library stay absolute
+        let stored = config.relative_audio_path(Path::new("/elsewhere/Mix.mp3"));
+        assert_eq!(stored, "/elsewhere/Mix.mp3");
+        assert_eq!(
+            config.resolve_audio_path(&stored),
+            PathBuf::from("/elsewhere/Mix.mp3")
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("500MB").unwrap(), 500 << 20);
+        assert_eq!(parse_size("1.5g").unwrap(), 3 << 29);
+        assert_eq!(parse_size("2 GiB").unwrap(), 2 << 30);
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("-1G").is_err());
+    }
+
+    #[test]
+    fn test_partial_download_section() {
+        let config: Config = toml::from_str("[download.retries]\nattempts = 5\n").unwrap();
+        assert_eq!(config.download.concurrency, 3);
+        assert_eq!(config.download.retries.attempts, 5);
+        assert_eq!(config.download.retries.backoff, 2);
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::download::probe_bitrate;
+
+/// An ffmpeg encoding target for `mixyt convert`.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub format: String,
+    pub extension: &'static str,
+    codec: &'static str,
+    /// ffmpeg bitrate, e.g. "128k"; the encoder's default when unset
+    bitrate: Option<String>,
+}
+
+impl Target {
+    pub fn new(format: &str, bitrate: Option<&str>) -> Result<Self> {
+        let format = format.to_lowercase();
+        let (extension, codec) = match format.as_str() {
+            "mp3" => ("mp3", "libmp3lame"),
+            "opus" => ("opus", "libopus"),
+            "vorbis" | "ogg" => ("ogg", "libvorbis"),
+            "aac" | "m4a" => ("m4a", "aac"),
+            "flac" => ("flac", "flac"),
+            "wav" => ("wav", "pcm_s16le"),
+            _ => bail!("Unsupported format '{format}'. Use mp3, opus, vorbis, aac, flac or wav"),
+        };
+
+        let bitrate = bitrate.map(|b| b.trim().to_lowercase());
+        if let Some(ref b) = bitrate
+            && !b
+                .strip_suffix('k')
+                .is_some_and(|kbps| kbps.parse::<u32>().is_ok_and(|n| n > 0))
+        {
+            bail!("Invalid bitrate '{b}'. Use a value like 128k");
+        }
+
+        Ok(Self {
+            format,
+            extension,
+            codec,
+            bitrate,
+        })
+    }
+
+    /// Whether a file already matches this target, so converting is pointless.
+    pub fn matches(&self, path: &Path) -> bool {
+        self.bitrate.is_none()
+            && path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(self.extension))
+    }
+}
+
+/// A converted file, waiting to take the original's place in the library.
+#[derive(Debug)]
+pub struct Converted {
+    pub path: PathBuf,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub bitrate: Option<u32>,
+}
+
+/// Re-encode `input` next to itself with ffmpeg.
+///
+/// The output is written to a temporary file and only renamed into place
+/// once it has been verified to decode. The original is left for the caller
+/// to delete once the database points at the new file.
+pub fn convert_file(input: &Path, target: &Target) -> Result<Converted> {
+    if !input.exists() {
+        bail!("Audio file not found: {}", input.display());
+    }
+
+    let old_size = fs::metadata(input)?.len();
+    let output = input.with_extension(target.extension);
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let temp = input.with_file_name(format!(".{stem}.converting.{}", target.extension));
+
+    let result = encode(input, &temp, target).and_then(|_| verify(&temp));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    fs::rename(&temp, &output)
+        .with_context(|| format!("Failed to move converted file to {}", output.display()))?;
+
+    Ok(Converted {
+        old_size,
+        new_size: fs::metadata(&output)?.len(),
+        bitrate: probe_bitrate(&output),
+        path: output,
+    })
+}
+
+fn encode(input: &Path, output: &Path, target: &Target) -> Result<()> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-y", "-v", "error", "-i"])
+        .arg(input)
+        .args(["-vn", "-c:a", target.codec]);
+    if let Some(ref bitrate) = target.bitrate {
+        command.args(["-b:a", bitrate]);
+    }
+
+    let output_result = command
+        .arg(output)
+        .output()
+        .with_context(|| "Failed to run ffmpeg")?;
+
+    if !output_result.status.success() {
+        bail!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output_result.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Decode the whole file to check the conversion produced valid audio.
+fn verify(path: &Path) -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-f", "null", "-"])
+        .output()
+        .with_context(|| "Failed to run ffmpeg")?;
+
+    if !output.status.success() {
+        bail!(
+            "Converted file doesn't decode: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target() {
+        let target = Target::new("Vorbis", None).unwrap();
+        assert_eq!(target.extension, "ogg");
+        assert!(target.matches(Path::new("Mix [abc].ogg")));
+        assert!(!target.matches(Path::new("Mix [abc].mp3")));
+
+        // Re-encoding to a new bitrate is never a no-op
+        let target = Target::new("mp3", Some("128K")).unwrap();
+        assert!(!target.matches(Path::new("Mix [abc].mp3")));
+
+        assert!(Target::new("mp3", Some("loud")).is_err());
+        assert!(Target::new("midi", None).is_err());
+    }
+}
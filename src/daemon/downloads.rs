@@ -0,0 +1,243 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::download::{DownloadError, Downloader, extract_start_time};
+use crate::models::{DownloadJob, JobStatus, Track};
+
+use super::evict_over_quota;
+
+/// The daemon's persistent download queue.
+///
+/// Jobs are mirrored in memory (with live progress) and written through to the
+/// database, so they survive daemon restarts.
+pub struct DownloadQueue {
+    db: Mutex<Database>,
+    jobs: Mutex<Vec<DownloadJob>>,
+    cancel_flags: Mutex<HashMap<Uuid, Arc<AtomicBool>>>,
+}
+
+impl DownloadQueue {
+    pub fn open(config: &Config) -> Result<Self> {
+        let db = Database::open(&config.db_path())?;
+        let mut jobs = db.get_download_jobs()?;
+
+        // Jobs interrupted by a restart start over
+        for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+            job.status = JobStatus::Queued;
+            db.update_download_job(job)?;
+        }
+
+        Ok(Self {
+            db: Mutex::new(db),
+            jobs: Mutex::new(jobs),
+            cancel_flags: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn enqueue(
+        &self,
+        url: String,
+        alias: Option<String>,
+        playlist: Option<String>,
+    ) -> Result<DownloadJob> {
+        let job = DownloadJob::new(url, alias, playlist);
+        self.db.lock().unwrap().insert_download_job(&job)?;
+        self.jobs.lock().unwrap().push(job.clone());
+        Ok(job)
+    }
+
+    pub fn list(&self) -> Vec<DownloadJob> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<DownloadJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = find_job(&mut jobs, id)?;
+
+        match job.status {
+            JobStatus::Queued => {
+                job.status = JobStatus::Cancelled;
+                self.db.lock().unwrap().update_download_job(job)?;
+            }
+            JobStatus::Running => {
+                // The worker marks the job cancelled once yt-dlp has been killed
+                if let Some(flag) = self.cancel_flags.lock().unwrap().get(&job.id) {
+                    flag.store(true, Ordering::SeqCst);
+                }
+            }
+            status => bail!("Download is already {status}"),
+        }
+
+        Ok(job.clone())
+    }
+
+    pub fn retry(&self, id: &str) -> Result<DownloadJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = find_job(&mut jobs, id)?;
+
+        if !matches!(job.status, JobStatus::Failed | JobStatus::Cancelled) {
+            bail!("Only failed or cancelled downloads can be retried");
+        }
+
+        job.status = JobStatus::Queued;
+        job.error = None;
+        self.db.lock().unwrap().update_download_job(job)?;
+
+        Ok(job.clone())
+    }
+
+    /// Remove finished jobs, returning how many were removed.
+    pub fn clear_finished(&self) -> Result<usize> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let db = self.db.lock().unwrap();
+
+        let before = jobs.len();
+        for job in jobs.iter().filter(|j| j.status.is_finished()) {
+            db.delete_download_job(&job.id)?;
+        }
+        jobs.retain(|j| !j.status.is_finished());
+
+        Ok(before - jobs.len())
+    }
+
+    /// Mark the oldest queued job as running and hand it to a worker.
+    fn claim_next(&self) -> Option<(DownloadJob, Arc<AtomicBool>)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.iter_mut().find(|j| j.status == JobStatus::Queued)?;
+
+        job.status = JobStatus::Running;
+        if let Err(e) = self.db.lock().unwrap().update_download_job(job) {
+            error!("Failed to update download job: {e}");
+        }
+
+        let flag = Arc::new(AtomicBool::new(false));
+        self.cancel_flags
+            .lock()
+            .unwrap()
+            .insert(job.id, Arc::clone(&flag));
+
+        Some((job.clone(), flag))
+    }
+
+    fn update(&self, id: &Uuid, apply: impl FnOnce(&mut DownloadJob)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == *id) {
+            apply(job);
+            if let Err(e) = self.db.lock().unwrap().update_download_job(job) {
+                error!("Failed to update download job: {e}");
+            }
+        }
+    }
+
+    fn set_progress(&self, id: &Uuid, progress: String) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == *id) {
+            job.progress = Some(progress);
+        }
+    }
+
+    fn finish(&self, id: &Uuid, result: Result<Track>, cancelled: bool) {
+        self.cancel_flags.lock().unwrap().remove(id);
+        self.update(id, |job| {
+            job.progress = None;
+            match result {
+                Ok(track) => {
+                    job.status = JobStatus::Done;
+                    job.title = Some(track.title);
+                    job.track_id = Some(track.id);
+                }
+                Err(_) if cancelled => job.status = JobStatus::Cancelled,
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(match e.downcast::<DownloadError>() {
+                        Ok(error) => error,
+                        Err(e) => DownloadError::Unknown(e.to_string()),
+                    });
+                }
+            }
+        });
+    }
+}
+
+fn find_job<'a>(jobs: &'a mut [DownloadJob], id: &str) -> Result<&'a mut DownloadJob> {
+    let mut matches = jobs.iter_mut().filter(|j| j.id.to_string().starts_with(id));
+
+    match (matches.next(), matches.next()) {
+        (Some(job), None) => Ok(job),
+        (Some(_), Some(_)) => bail!("Download ID '{id}' is ambiguous"),
+        (None, _) => bail!("No download found with ID '{id}'"),
+    }
+}
+
+pub fn run_download_worker(queue: Arc<DownloadQueue>, config: Config, running: Arc<AtomicBool>) {
+    let db = match Database::open(&config.db_path()) {
+        Ok(db) => db,
+        Err(e) => {
+            error!("Download worker failed to open database: {e}");
+            return;
+        }
+    };
+    let downloader = Downloader::new(config.clone());
+
+    while running.load(Ordering::SeqCst) {
+        let Some((job, cancel)) = queue.claim_next() else {
+            thread::sleep(std::time::Duration::from_millis(500));
+            continue;
+        };
+
+        info!("Downloading {}", job.url);
+        let result = process_job(&queue, &downloader, &db, &job, &cancel);
+
+        match &result {
+            Ok(track) => {
+                info!("Downloaded {}", track.title);
+                evict_over_quota(&config, &db, &[track.id]);
+            }
+            Err(e) => warn!("Download of {} failed: {e}", job.url),
+        }
+        queue.finish(&job.id, result, cancel.load(Ordering::SeqCst));
+    }
+}
+
+fn process_job(
+    queue: &DownloadQueue,
+    downloader: &Downloader,
+    db: &Database,
+    job: &DownloadJob,
+    cancel: &AtomicBool,
+) -> Result<Track> {
+    let (title, canonical_url, _) = downloader.get_video_info(&job.url)?;
+    queue.update(&job.id, |j| j.title = Some(title));
+
+    if cancel.load(Ordering::SeqCst) {
+        return Err(DownloadError::Cancelled.into());
+    }
+
+    let track = match db.get_track_by_source(&canonical_url)? {
+        Some(existing) => existing,
+        None => {
+            let start = extract_start_time(&job.url);
+            let mut track =
+                downloader.download_cancellable(&canonical_url, start, cancel, |phase| {
+                    queue.set_progress(&job.id, phase.to_string());
+                })?;
+            track.alias = job.alias.clone();
+            db.insert_track(&track)?;
+            track
+        }
+    };
+
+    if let Some(ref name) = job.playlist {
+        let playlist = db.get_or_create_playlist(name)?;
+        db.add_track_to_playlist(&playlist.id, &track.id)?;
+    }
+
+    Ok(track)
+}
@@ -1,28 +1,60 @@
+mod downloads;
+
 use anyhow::{Context, Result};
+use chrono::Utc;
 use std::fs;
 use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tracing::{error, info, warn};
 
-use crate::audio::AudioPlayer;
+use crate::audio::{AudioPlayer, GrowingFile, StreamSource};
 use crate::config::Config;
+use crate::db::Database;
+use crate::download::{DownloadError, Downloader};
 use crate::ipc::{DaemonCommand, DaemonResponse};
-use crate::models::{PlaybackState, RepeatMode, Track};
+use crate::models::{Chapter, LoopRegion, PlaybackState, RepeatMode, Track};
+use crate::storage;
+use crate::subscription::{SyncEvent, sync_subscription};
+use downloads::{DownloadQueue, run_download_worker};
+
+/// How often the audio thread checks for commands and the A-B loop.
+const LOOP_CHECK_MS: u64 = 50;
+
+/// Stream data to wait for before decoding starts, about 15s of MP3.
+const STREAM_PREBUFFER_BYTES: u64 = 256 * 1024;
+
+/// How long a stream may take to prebuffer before it's given up on.
+const STREAM_START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How far into a chapter going to the previous one restarts it instead.
+const CHAPTER_RESTART_SECS: u64 = 3;
 
 // Internal commands for the audio thread
-#[derive(Clone)]
 enum AudioCommand {
     Play(Track),
+    /// An evicted track has been downloaded again
+    Restored(Track),
+    PlayStream {
+        track: Track,
+        save: bool,
+    },
+    /// A stream has prebuffered and is ready to play
+    StreamReady {
+        track: Track,
+        source: Box<StreamSource<rodio::Decoder<GrowingFile>>>,
+    },
     Pause,
     Resume,
     Stop,
     SetVolume(u8),
-    Seek(u64),
+    Seek(std::time::Duration),
+    /// Seek to the start of a new A-B loop unless already inside it
+    EnterLoop(LoopRegion),
     CheckFinished(Sender<bool>),
-    GetPosition(Sender<u64>),
 }
 
 pub struct Daemon {
@@ -49,6 +81,10 @@ impl Daemon {
         let pid_path = self.config.pid_path();
         fs::write(&pid_path, std::process::id().to_string())?;
 
+        // Drop streams that were playing when the daemon last stopped
+        let _ = fs::remove_dir_all(self.config.stream_dir());
+        fs::create_dir_all(self.config.stream_dir())?;
+
         // Create listener
         let name = socket_path.as_os_str().to_fs_name::<GenericFilePath>()?;
         let listener = ListenerOptions::new()
@@ -70,9 +106,16 @@ impl Daemon {
         // Spawn audio thread - AudioPlayer stays on this single thread
         let audio_running = Arc::clone(&running);
         let audio_state = Arc::clone(&state);
-        let default_volume = self.config.playback.default_volume;
+        let audio_config = self.config.clone();
+        let restore_tx = audio_tx.clone();
         thread::spawn(move || {
-            run_audio_thread(audio_rx, audio_state, audio_running, default_volume);
+            run_audio_thread(
+                audio_rx,
+                restore_tx,
+                audio_state,
+                audio_running,
+                audio_config,
+            );
         });
 
         // Spawn playback monitor thread
@@ -83,6 +126,26 @@ impl Daemon {
             playback_monitor(monitor_state, monitor_running, monitor_audio_tx);
         });
 
+        // Spawn download queue workers
+        let queue = Arc::new(DownloadQueue::open(&self.config)?);
+        for _ in 0..self.config.download.concurrency.max(1) {
+            let worker_queue = Arc::clone(&queue);
+            let worker_config = self.config.clone();
+            let worker_running = Arc::clone(&running);
+            thread::spawn(move || {
+                run_download_worker(worker_queue, worker_config, worker_running);
+            });
+        }
+
+        // Spawn subscription sync scheduler
+        if self.config.daemon.sync_interval > 0 {
+            let sync_config = self.config.clone();
+            let sync_running = Arc::clone(&running);
+            thread::spawn(move || {
+                sync_scheduler(sync_config, sync_running);
+            });
+        }
+
         // Initialize media controls (for system media keys)
         let media_controls = init_media_controls(Arc::clone(&state), audio_tx.clone());
         if media_controls.is_none() {
@@ -102,7 +165,7 @@ impl Daemon {
         while running.load(Ordering::SeqCst) {
             match listener.accept() {
                 Ok(conn) => {
-                    let response = handle_connection(conn, &state, &running, &audio_tx);
+                    let response = handle_connection(conn, &state, &running, &audio_tx, &queue);
 
                     if let Err(e) = response {
                         error!("Connection error: {e}");
@@ -239,15 +302,15 @@ fn update_media_controls_loop(
 ) {
     use souvlaki::{MediaMetadata, MediaPlayback};
 
-    let mut last_track_id: Option<uuid::Uuid> = None;
+    let mut last_track: Option<(uuid::Uuid, Option<usize>)> = None;
     let mut last_playing: Option<bool> = None;
 
     while running.load(Ordering::SeqCst) {
         thread::sleep(std::time::Duration::from_millis(500));
 
-        let (current_track, is_playing) = {
+        let (current_track, chapter, is_playing) = {
             let s = state.lock().unwrap();
-            (s.current_track.clone(), s.is_playing)
+            (s.current_track.clone(), s.chapter, s.is_playing)
         };
 
         // Update playback state if changed
@@ -263,19 +326,23 @@ fn update_media_controls_loop(
             last_playing = Some(is_playing);
         }
 
-        // Update metadata if track changed
+        // Update metadata if the track or chapter changed. Within a mix the
+        // chapter is the title and the mix the album
         if let Some(ref track) = current_track {
-            if last_track_id != Some(track.id) {
+            if last_track != Some((track.id, chapter)) {
+                let chapter_title = chapter
+                    .and_then(|i| track.chapters.get(i))
+                    .map(|c| c.title.as_str());
                 let _ = controls.set_metadata(MediaMetadata {
-                    title: Some(&track.title),
-                    artist: Some("mixyt"),
-                    album: None,
+                    title: Some(chapter_title.unwrap_or(track.display_title())),
+                    artist: Some(track.artist.as_deref().unwrap_or("mixyt")),
+                    album: chapter_title.map(|_| track.display_title()),
                     cover_url: None,
                     duration: Some(std::time::Duration::from_secs(track.duration)),
                 });
-                last_track_id = Some(track.id);
+                last_track = Some((track.id, chapter));
             }
-        } else if last_track_id.is_some() {
+        } else if last_track.is_some() {
             let _ = controls.set_metadata(MediaMetadata {
                 title: None,
                 artist: None,
@@ -283,20 +350,29 @@ fn update_media_controls_loop(
                 cover_url: None,
                 duration: None,
             });
-            last_track_id = None;
+            last_track = None;
         }
     }
 }
 
 fn run_audio_thread(
     rx: Receiver<AudioCommand>,
+    restore_tx: Sender<AudioCommand>,
     state: Arc<Mutex<PlaybackState>>,
     running: Arc<AtomicBool>,
-    default_volume: u8,
+    config: Config,
 ) {
+    let db = match Database::open(&config.db_path()) {
+        Ok(db) => Some(db),
+        Err(e) => {
+            warn!("Audio thread failed to open database, plays won't be recorded: {e}");
+            None
+        }
+    };
+
     let player = match AudioPlayer::new() {
         Ok(p) => {
-            p.set_volume(default_volume);
+            p.set_volume(config.playback.default_volume);
             Some(p)
         }
         Err(e) => {
@@ -305,21 +381,72 @@ fn run_audio_thread(
         }
     };
 
+    let mut stream: Option<ActiveStream> = None;
+    // Evicted track being downloaded again to be played
+    let mut restoring: Option<uuid::Uuid> = None;
+
     while running.load(Ordering::SeqCst) {
-        match rx.recv_timeout(std::time::Duration::from_millis(100)) {
+        if let Some(ref p) = player {
+            update_position(p, &state);
+            keep_in_loop(p, &state);
+        }
+
+        match rx.recv_timeout(std::time::Duration::from_millis(LOOP_CHECK_MS)) {
             Ok(cmd) => {
                 if let Some(ref p) = player {
+                    // A newer request replaces any stream or restore
+                    if matches!(
+                        cmd,
+                        AudioCommand::Play(_)
+                            | AudioCommand::PlayStream { .. }
+                            | AudioCommand::Stop
+                    ) {
+                        restoring = None;
+                        if let Some(previous) = stream.take() {
+                            previous.end();
+                        }
+                    }
+
                     match cmd {
                         AudioCommand::Play(track) => {
-                            let path = std::path::Path::new(&track.file_path);
-                            if let Err(e) = p.play_file(path) {
-                                error!("Failed to play: {e}");
-                                state.lock().unwrap().is_playing = false;
-                            } else {
+                            // Queued copies may predate an eviction
+                            let track = db
+                                .as_ref()
+                                .and_then(|db| db.get_track(&track.id).ok().flatten())
+                                .unwrap_or(track);
+
+                            if track.remote_only {
+                                restoring = Some(track.id);
+                                let (config, tx) = (config.clone(), restore_tx.clone());
+                                thread::spawn(move || restore_and_play(config, track, tx));
+                                continue;
+                            }
+
+                            play_local(p, db.as_ref(), &config, &state, track);
+                        }
+                        AudioCommand::Restored(track) => {
+                            // Dropped if something else was asked for since
+                            if restoring == Some(track.id) {
+                                restoring = None;
+                                play_local(p, db.as_ref(), &config, &state, track);
+                            }
+                        }
+                        AudioCommand::PlayStream { track, save } => {
+                            match start_stream(&config, &track, save, restore_tx.clone()) {
+                                Ok(active) => stream = Some(active),
+                                Err(e) => error!("Failed to stream {}: {e}", track.url),
+                            }
+                        }
+                        AudioCommand::StreamReady { track, source } => {
+                            // Streams replaced or stopped while they
+                            // buffered are dropped
+                            if stream.as_ref().is_some_and(|s| s.track_id == track.id) {
+                                p.play_stream(*source);
                                 let mut s = state.lock().unwrap();
                                 s.current_track = Some(track);
                                 s.is_playing = true;
-                                s.position = 0;
+                                s.loop_region = None;
+                                s.set_position(0);
                             }
                         }
                         AudioCommand::Pause => {
@@ -335,24 +462,32 @@ fn run_audio_thread(
                             let mut s = state.lock().unwrap();
                             s.is_playing = false;
                             s.current_track = None;
-                            s.position = 0;
+                            s.loop_region = None;
+                            s.set_position(0);
                         }
                         AudioCommand::SetVolume(vol) => {
                             p.set_volume(vol);
                             state.lock().unwrap().volume = vol;
                         }
                         AudioCommand::Seek(position) => {
-                            let duration = std::time::Duration::from_secs(position);
-                            if p.seek(duration) {
-                                state.lock().unwrap().position = position;
+                            if p.seek(position) {
+                                state.lock().unwrap().set_precise_position(position);
                             }
                         }
-                        AudioCommand::CheckFinished(response_tx) => {
-                            let _ = response_tx.send(p.is_finished());
+                        AudioCommand::EnterLoop(region) => {
+                            if !(region.start..region.end).contains(&p.get_position())
+                                && p.seek(region.start)
+                            {
+                                state.lock().unwrap().set_precise_position(region.start);
+                            }
                         }
-                        AudioCommand::GetPosition(response_tx) => {
-                            let pos = p.get_position().as_secs();
-                            let _ = response_tx.send(pos);
+                        AudioCommand::CheckFinished(response_tx) => {
+                            // A trimmed track is still playing at its end
+                            let finished = p.is_finished();
+                            if finished {
+                                p.stop();
+                            }
+                            let _ = response_tx.send(finished);
                         }
                     }
                 }
@@ -363,6 +498,192 @@ fn run_audio_thread(
     }
 }
 
+/// Copy the player's position into the shared state while playing, so
+/// clients see it to within `LOOP_CHECK_MS`.
+fn update_position(player: &AudioPlayer, state: &Mutex<PlaybackState>) {
+    let mut s = state.lock().unwrap();
+    if s.is_playing && s.current_track.is_some() {
+        s.set_precise_position(player.get_position());
+    }
+}
+
+/// Play a track from its file in the library, recording the play.
+fn play_local(
+    player: &AudioPlayer,
+    db: Option<&Database>,
+    config: &Config,
+    state: &Mutex<PlaybackState>,
+    track: Track,
+) {
+    let path = config.resolve_audio_path(&track.file_path);
+    let start = track.play_start();
+    let end = track
+        .trim_end
+        .map(|_| std::time::Duration::from_secs(track.play_end()));
+    let start_at = std::time::Duration::from_secs(start);
+    if let Err(e) = player.play_file(&path, start_at, end) {
+        error!("Failed to play: {e}");
+        state.lock().unwrap().is_playing = false;
+        return;
+    }
+
+    if let Some(db) = db
+        && let Err(e) = db.update_track_played(&track.id, Utc::now())
+    {
+        warn!("Failed to record play of {}: {e}", track.title);
+    }
+    let mut s = state.lock().unwrap();
+    s.current_track = Some(track);
+    s.is_playing = true;
+    s.loop_region = None;
+    s.set_position(start);
+}
+
+/// Jump back to the start of the A-B loop once playback passes its end.
+fn keep_in_loop(player: &AudioPlayer, state: &Mutex<PlaybackState>) {
+    let Some(region) = state.lock().unwrap().loop_region else {
+        return;
+    };
+
+    if player.get_position() >= region.end && player.seek(region.start) {
+        state.lock().unwrap().set_precise_position(region.start);
+    }
+}
+
+/// A track being played while it downloads.
+struct ActiveStream {
+    track_id: uuid::Uuid,
+    path: PathBuf,
+    cancel: Arc<AtomicBool>,
+    /// Tells the prebuffering thread to give up
+    closed: Arc<AtomicBool>,
+    save: bool,
+}
+
+impl ActiveStream {
+    /// Stop an ephemeral stream's download and delete its file. Streams being
+    /// saved keep downloading in the background.
+    fn end(self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if !self.save {
+            self.cancel.store(true, Ordering::SeqCst);
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Start downloading `track` into the stream directory. Once enough has
+/// arrived it is decoded on another thread and sent back to the audio thread
+/// as `StreamReady`, so a stalled download never blocks playback commands.
+fn start_stream(
+    config: &Config,
+    track: &Track,
+    save: bool,
+    audio_tx: Sender<AudioCommand>,
+) -> Result<ActiveStream> {
+    let path = config.stream_dir().join(format!("{}.mp3", track.id));
+    fs::File::create(&path)?;
+
+    let complete = Arc::new(AtomicBool::new(false));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let closed = Arc::new(AtomicBool::new(false));
+    let reader = GrowingFile::open(&path, Arc::clone(&complete), Arc::clone(&closed))?;
+
+    let (ready_track, ready_cancel) = (track.clone(), Arc::clone(&cancel));
+    thread::spawn(move || {
+        match StreamSource::decode(reader, STREAM_PREBUFFER_BYTES, STREAM_START_TIMEOUT) {
+            Ok(source) => {
+                let _ = audio_tx.send(AudioCommand::StreamReady {
+                    track: ready_track,
+                    source: Box::new(source),
+                });
+            }
+            Err(e) => {
+                error!("Failed to stream {}: {e}", ready_track.url);
+                if !save {
+                    ready_cancel.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    });
+
+    let track_id = track.id;
+    let (config, track, output) = (config.clone(), track.clone(), path.clone());
+    let download_cancel = Arc::clone(&cancel);
+    thread::spawn(move || {
+        let downloader = Downloader::new(config.clone());
+        let result = downloader.stream(&track.url, &output, &download_cancel);
+        complete.store(true, Ordering::SeqCst);
+
+        match result {
+            Ok(()) if save => {
+                if let Err(e) = save_stream(&config, track, &output) {
+                    error!("Failed to save stream: {e}");
+                }
+            }
+            Ok(()) | Err(DownloadError::Cancelled) => {}
+            Err(e) => error!("Streaming {} failed: {e}", track.title),
+        }
+    });
+
+    Ok(ActiveStream {
+        track_id,
+        path,
+        cancel,
+        closed,
+        save,
+    })
+}
+
+/// Move a finished stream into the library as a normal track.
+fn save_stream(config: &Config, track: Track, path: &Path) -> Result<()> {
+    let db = Database::open(&config.db_path())?;
+    if db.get_track_by_source(&track.url)?.is_some() {
+        info!("{} is already in the library", track.title);
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    let mut track = Downloader::new(config.clone()).save_stream(track, path)?;
+    track.last_played_at = Some(Utc::now());
+    db.insert_track(&track)?;
+    info!("Saved {} to the library", track.title);
+
+    evict_over_quota(config, &db, &[track.id]);
+    Ok(())
+}
+
+/// Download an evicted track again, then play it unless something else has
+/// been played or stopped in the meantime.
+fn restore_and_play(config: Config, track: Track, audio_tx: Sender<AudioCommand>) {
+    info!("Downloading evicted track {}", track.title);
+
+    let result = Database::open(&config.db_path()).and_then(|db| {
+        let downloader = Downloader::new(config.clone());
+        let restored = storage::restore_track(&downloader, &db, &track, |_| {})?;
+        evict_over_quota(&config, &db, &[restored.id]);
+        Ok(restored)
+    });
+
+    match result {
+        Ok(restored) => {
+            let _ = audio_tx.send(AudioCommand::Restored(restored));
+        }
+        Err(e) => error!("Failed to download {}: {e}", track.title),
+    }
+}
+
+fn evict_over_quota(config: &Config, db: &Database, keep: &[uuid::Uuid]) {
+    match storage::enforce_quota(config, db, keep) {
+        Ok(evicted) => {
+            for track in evicted {
+                info!("Evicted {} to stay under storage.max_size", track.title);
+            }
+        }
+        Err(e) => warn!("Failed to enforce storage quota: {e}"),
+    }
+}
+
 fn playback_monitor(
     state: Arc<Mutex<PlaybackState>>,
     running: Arc<AtomicBool>,
@@ -380,14 +701,6 @@ fn playback_monitor(
             continue;
         }
 
-        // Get real position from audio player
-        let (pos_tx, pos_rx) = mpsc::channel();
-        if audio_tx.send(AudioCommand::GetPosition(pos_tx)).is_ok()
-            && let Ok(pos) = pos_rx.recv_timeout(std::time::Duration::from_millis(100))
-        {
-            state.lock().unwrap().position = pos;
-        }
-
         // Check if audio finished
         let (tx, rx) = mpsc::channel();
         let sent = audio_tx.send(AudioCommand::CheckFinished(tx)).is_ok();
@@ -401,16 +714,95 @@ fn playback_monitor(
             // Track finished, stop playback
             s.is_playing = false;
             s.current_track = None;
-            s.position = 0;
+            s.loop_region = None;
+            s.set_position(0);
         }
     }
 }
 
+fn sync_scheduler(config: Config, running: Arc<AtomicBool>) {
+    let interval = std::time::Duration::from_secs(config.daemon.sync_interval * 60);
+    let mut last_sync = std::time::Instant::now();
+
+    while running.load(Ordering::SeqCst) {
+        thread::sleep(std::time::Duration::from_secs(1));
+
+        if last_sync.elapsed() < interval {
+            continue;
+        }
+        last_sync = std::time::Instant::now();
+
+        if let Err(e) = run_scheduled_sync(&config) {
+            error!("Scheduled sync failed: {e}");
+        }
+    }
+}
+
+fn run_scheduled_sync(config: &Config) -> Result<()> {
+    let db = Database::open(&config.db_path())?;
+    let downloader = Downloader::new(config.clone());
+
+    for subscription in db.get_all_subscriptions()? {
+        let result = sync_subscription(&downloader, &db, &subscription, |event| {
+            if let SyncEvent::Failed(entry, e) = event {
+                warn!("Failed to download {}: {e}", entry.title);
+            }
+        });
+
+        match result {
+            Ok(report) => info!(
+                "Synced {}: {} added, {} failed",
+                subscription.title, report.added, report.failed
+            ),
+            Err(e) => warn!("Failed to sync {}: {e}", subscription.title),
+        }
+    }
+
+    evict_over_quota(config, &db, &[]);
+
+    Ok(())
+}
+
+/// Seek to the start of the chapter of the current track `pick` chooses.
+fn seek_chapter(
+    state: &Arc<Mutex<PlaybackState>>,
+    audio_tx: &Sender<AudioCommand>,
+    pick: impl FnOnce(&PlaybackState, &[Chapter]) -> Result<usize, String>,
+) -> DaemonResponse {
+    let start = {
+        let s = state.lock().unwrap();
+        let Some(ref track) = s.current_track else {
+            return DaemonResponse::Error("Nothing is playing".to_string());
+        };
+        if track.chapters.is_empty() {
+            return DaemonResponse::Error(format!("'{}' has no chapters", track.display_name()));
+        }
+        match pick(&s, &track.chapters) {
+            Ok(index) => track.chapters[index].start,
+            Err(e) => return DaemonResponse::Error(e),
+        }
+    };
+
+    let _ = audio_tx.send(AudioCommand::Seek(std::time::Duration::from_secs(start)));
+    DaemonResponse::Ok
+}
+
+/// Like a CD player: the start of the current chapter, or the one before
+/// if it only just started.
+fn previous_chapter(s: &PlaybackState, chapters: &[Chapter]) -> usize {
+    match s.chapter {
+        Some(i) if s.position >= chapters[i].start + CHAPTER_RESTART_SECS => i,
+        Some(i) => i.saturating_sub(1),
+        None => 0,
+    }
+}
+
 fn handle_connection(
     conn: interprocess::local_socket::Stream,
     state: &Arc<Mutex<PlaybackState>>,
     running: &Arc<AtomicBool>,
     audio_tx: &Sender<AudioCommand>,
+    queue: &DownloadQueue,
 ) -> Result<()> {
     let mut reader = BufReader::new(&conn);
     let mut writer = &conn;
@@ -419,7 +811,7 @@ fn handle_connection(
     reader.read_line(&mut line)?;
 
     let command: DaemonCommand = serde_json::from_str(&line)?;
-    let response = handle_command(command, state, running, audio_tx);
+    let response = handle_command(command, state, running, audio_tx, queue);
 
     let response_json = serde_json::to_string(&response)?;
     writeln!(writer, "{response_json}")?;
@@ -433,6 +825,7 @@ fn handle_command(
     state: &Arc<Mutex<PlaybackState>>,
     running: &Arc<AtomicBool>,
     audio_tx: &Sender<AudioCommand>,
+    queue: &DownloadQueue,
 ) -> DaemonResponse {
     match command {
         DaemonCommand::Play { track } => {
@@ -465,6 +858,16 @@ fn handle_command(
                 DaemonResponse::Error("Audio thread not running".to_string())
             }
         }
+        DaemonCommand::PlayStream { track, save } => {
+            if audio_tx
+                .send(AudioCommand::PlayStream { track, save })
+                .is_ok()
+            {
+                DaemonResponse::Ok
+            } else {
+                DaemonResponse::Error("Audio thread not running".to_string())
+            }
+        }
         DaemonCommand::Pause => {
             let _ = audio_tx.send(AudioCommand::Pause);
             DaemonResponse::Ok
@@ -533,7 +936,53 @@ fn handle_command(
             }
         }
         DaemonCommand::Seek { position } => {
-            let _ = audio_tx.send(AudioCommand::Seek(position));
+            let _ = audio_tx.send(AudioCommand::Seek(std::time::Duration::from_secs(position)));
+            DaemonResponse::Ok
+        }
+        DaemonCommand::NextChapter => seek_chapter(state, audio_tx, |s, chapters| {
+            let next = s.chapter.map_or(0, |i| i + 1);
+            if next < chapters.len() {
+                Ok(next)
+            } else {
+                Err("Already in the last chapter".to_string())
+            }
+        }),
+        DaemonCommand::PreviousChapter => seek_chapter(state, audio_tx, |s, chapters| {
+            Ok(previous_chapter(s, chapters))
+        }),
+        DaemonCommand::Chapter { index } => seek_chapter(state, audio_tx, |_, chapters| {
+            if index < chapters.len() {
+                Ok(index)
+            } else {
+                Err(format!(
+                    "There is no chapter {}, the track has {}",
+                    index + 1,
+                    chapters.len()
+                ))
+            }
+        }),
+        DaemonCommand::SetLoop { region } => {
+            let mut s = state.lock().unwrap();
+            if let Some(region) = region {
+                let Some(ref track) = s.current_track else {
+                    return DaemonResponse::Error("Nothing is playing".to_string());
+                };
+                if region.end <= region.start {
+                    return DaemonResponse::Error("B must come after A".to_string());
+                }
+                if region.start < std::time::Duration::from_secs(track.play_start()) {
+                    return DaemonResponse::Error("A is before the start of the track".to_string());
+                }
+                let end = std::time::Duration::from_secs(track.play_end());
+                if track.duration > 0 && region.end > end {
+                    return DaemonResponse::Error("B is past the end of the track".to_string());
+                }
+            }
+            s.loop_region = region;
+
+            if let Some(region) = region {
+                let _ = audio_tx.send(AudioCommand::EnterLoop(region));
+            }
             DaemonResponse::Ok
         }
         DaemonCommand::SetVolume { volume } => {
@@ -558,9 +1007,30 @@ fn handle_command(
             s.queue_index = 0;
             DaemonResponse::Ok
         }
+        DaemonCommand::EnqueueDownload {
+            url,
+            alias,
+            playlist,
+        } => match queue.enqueue(url, alias, playlist) {
+            Ok(job) => DaemonResponse::Downloads(vec![job]),
+            Err(e) => DaemonResponse::Error(e.to_string()),
+        },
+        DaemonCommand::ListDownloads => DaemonResponse::Downloads(queue.list()),
+        DaemonCommand::CancelDownload { id } => match queue.cancel(&id) {
+            Ok(job) => DaemonResponse::Downloads(vec![job]),
+            Err(e) => DaemonResponse::Error(e.to_string()),
+        },
+        DaemonCommand::RetryDownload { id } => match queue.retry(&id) {
+            Ok(job) => DaemonResponse::Downloads(vec![job]),
+            Err(e) => DaemonResponse::Error(e.to_string()),
+        },
+        DaemonCommand::ClearDownloads => match queue.clear_finished() {
+            Ok(_) => DaemonResponse::Downloads(queue.list()),
+            Err(e) => DaemonResponse::Error(e.to_string()),
+        },
         DaemonCommand::GetStatus => {
             let s = state.lock().unwrap().clone();
-            DaemonResponse::Status(s)
+            DaemonResponse::Status(Box::new(s))
         }
         DaemonCommand::Shutdown => {
             running.store(false, Ordering::SeqCst);
@@ -569,3 +1039,45 @@ fn handle_command(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_previous_chapter_restarts_first() {
+        let mut track = Track::new("mix".to_string(), "Mix".to_string(), 600, String::new());
+        track.chapters = [("Intro", 0, 100), ("Middle", 100, 300), ("Outro", 300, 600)]
+            .into_iter()
+            .map(|(title, start, end)| Chapter {
+                title: title.to_string(),
+                start,
+                end,
+            })
+            .collect();
+
+        let seek_previous = |position| {
+            let mut s = PlaybackState::new();
+            s.current_track = Some(track.clone());
+            s.set_position(position);
+            let state = Arc::new(Mutex::new(s));
+            let (tx, rx) = mpsc::channel();
+            let response =
+                seek_chapter(&state, &tx, |s, chapters| Ok(previous_chapter(s, chapters)));
+            assert!(matches!(response, DaemonResponse::Ok));
+            match rx.try_recv() {
+                Ok(AudioCommand::Seek(to)) => to.as_secs(),
+                _ => panic!("no seek for position {position}"),
+            }
+        };
+
+        // Into a chapter: back to its start
+        assert_eq!(seek_previous(150), 100);
+        assert_eq!(seek_previous(100 + CHAPTER_RESTART_SECS), 100);
+        // Only just started: the chapter before
+        assert_eq!(seek_previous(100 + CHAPTER_RESTART_SECS - 1), 0);
+        assert_eq!(seek_previous(300), 100);
+        // The first chapter has nothing before it
+        assert_eq!(seek_previous(1), 0);
+    }
+}
@@ -1,10 +1,66 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use chrono::{DateTime, Utc};
-use rusqlite::{Connection, Row, params};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, params};
+use std::collections::HashSet;
 use std::path::Path;
 use uuid::Uuid;
 
-use crate::models::{Playlist, PlaylistTrack, Track};
+use crate::download::{DownloadError, extract_video_id};
+use crate::models::{
+    Bookmark, Chapter, DownloadJob, FileStatus, JobStatus, Playlist, PlaylistTrack, SourceStatus,
+    Subscription, Track, TrackAlias, TrashedTrack,
+};
+
+/// Columns read by `row_to_track`, in order.
+const TRACK_COLUMNS: &str = "id, url, title, alias, duration, added_at, file_path, \
+     file_status, file_checked_at, source_status, source_checked_at, \
+     format, bitrate, last_played_at, remote_only, video_id, custom_title, artist, notes, chapters, \
+     trim_start, trim_end, bookmarks";
+
+/// Schema changes made after the initial tables, applied in order on open.
+/// Append only: each entry's index is recorded in `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    // Audio format and bitrate of stored tracks
+    "ALTER TABLE tracks ADD COLUMN format TEXT;
+     ALTER TABLE tracks ADD COLUMN bitrate INTEGER;",
+    // Least-recently-played eviction for the storage quota
+    "ALTER TABLE tracks ADD COLUMN last_played_at TEXT;
+     ALTER TABLE tracks ADD COLUMN remote_only INTEGER NOT NULL DEFAULT 0;",
+    // Incremental availability checks
+    "ALTER TABLE tracks ADD COLUMN last_checked_at TEXT;",
+    // Separate states for the local file and the source video. `available`
+    // was cleared for either, so a cleared flag is taken as the source
+    "ALTER TABLE tracks ADD COLUMN file_status TEXT NOT NULL DEFAULT 'present';
+     ALTER TABLE tracks ADD COLUMN file_checked_at TEXT;
+     ALTER TABLE tracks ADD COLUMN source_status TEXT NOT NULL DEFAULT 'available';
+     ALTER TABLE tracks RENAME COLUMN last_checked_at TO source_checked_at;
+     UPDATE tracks SET source_status = 'unavailable' WHERE available = 0;
+     ALTER TABLE tracks DROP COLUMN available;",
+    // Duplicate detection by video ID; filled in by `backfill_video_ids`
+    "ALTER TABLE tracks ADD COLUMN video_id TEXT;
+     CREATE UNIQUE INDEX idx_tracks_video_id ON tracks(video_id);",
+    // Metadata edited with `mixyt edit`
+    "ALTER TABLE tracks ADD COLUMN custom_title TEXT;
+     ALTER TABLE tracks ADD COLUMN artist TEXT;
+     ALTER TABLE tracks ADD COLUMN notes TEXT;",
+    // Several unique aliases per track, `alias` being the one shown. Where
+    // tracks shared an alias the first added keeps it
+    "INSERT OR IGNORE INTO aliases (alias, track_id)
+     SELECT alias, id FROM tracks WHERE alias IS NOT NULL ORDER BY added_at;
+     UPDATE tracks SET alias = NULL WHERE alias IS NOT NULL
+       AND id != (SELECT track_id FROM aliases WHERE aliases.alias = tracks.alias);
+     ALTER TABLE trash ADD COLUMN aliases TEXT NOT NULL DEFAULT '[]';",
+    // Chapters of long mixes, as JSON
+    "ALTER TABLE tracks ADD COLUMN chapters TEXT NOT NULL DEFAULT '[]';",
+    // Playback window set with `mixyt trim`
+    "ALTER TABLE tracks ADD COLUMN trim_start INTEGER;
+     ALTER TABLE tracks ADD COLUMN trim_end INTEGER;",
+    // Named positions set with `mixyt mark`, as JSON
+    "ALTER TABLE tracks ADD COLUMN bookmarks TEXT NOT NULL DEFAULT '[]';",
+];
+
+/// Schema version of a fully migrated database.
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
 
 pub struct Database {
     conn: Connection,
@@ -20,6 +76,14 @@ impl Database {
         Ok(db)
     }
 
+    /// Open an existing database as it is, without creating or migrating
+    /// anything.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open database at {}", path.display()))?;
+        Ok(Self { conn })
+    }
+
     #[allow(dead_code)]
     pub fn open_in_memory() -> Result<Self> {
         let conn =
@@ -31,6 +95,57 @@ impl Database {
     }
 
     fn init(&self) -> Result<()> {
+        self.create_tables()?;
+        self.migrate()?;
+        self.backfill_video_ids()
+    }
+
+    /// Number of `MIGRATIONS` applied.
+    pub fn schema_version(&self) -> Result<usize> {
+        let version = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version)
+    }
+
+    /// Apply pending `MIGRATIONS`, tracking progress in `PRAGMA user_version`.
+    fn migrate(&self) -> Result<()> {
+        let version = self.schema_version()?;
+
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+            let migration = format!("BEGIN; {sql} PRAGMA user_version = {}; COMMIT;", i + 1);
+            self.conn
+                .execute_batch(&migration)
+                .with_context(|| format!("Failed to apply database migration {}", i + 1))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the video ID of tracks added before it was stored. Duplicates of
+    /// a video keep none until `mixyt dedupe` merges them.
+    fn backfill_video_ids(&self) -> Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, url FROM tracks WHERE video_id IS NULL")?;
+        let rows: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for (id, url) in rows {
+            if let Some(video_id) = extract_video_id(&url) {
+                self.conn.execute(
+                    "UPDATE OR IGNORE tracks SET video_id = ?1 WHERE id = ?2",
+                    params![video_id, id],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_tables(&self) -> Result<()> {
         self.conn.execute_batch(
             r#"
             CREATE TABLE IF NOT EXISTS tracks (
@@ -59,8 +174,58 @@ impl Database {
                 FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS subscriptions (
+                id TEXT PRIMARY KEY,
+                url TEXT NOT NULL UNIQUE,
+                title TEXT NOT NULL,
+                playlist_id TEXT,
+                filter TEXT,
+                created_at TEXT NOT NULL,
+                last_synced_at TEXT,
+                FOREIGN KEY (playlist_id) REFERENCES playlists(id) ON DELETE SET NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS subscription_entries (
+                subscription_id TEXT NOT NULL,
+                video_id TEXT NOT NULL,
+                PRIMARY KEY (subscription_id, video_id),
+                FOREIGN KEY (subscription_id) REFERENCES subscriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS download_jobs (
+                id TEXT PRIMARY KEY,
+                url TEXT NOT NULL,
+                alias TEXT,
+                playlist TEXT,
+                status TEXT NOT NULL,
+                title TEXT,
+                error TEXT,
+                track_id TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS aliases (
+                alias TEXT PRIMARY KEY,
+                track_id TEXT NOT NULL,
+                FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS one_off_tasks (
+                name TEXT PRIMARY KEY,
+                done_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS trash (
+                track_id TEXT PRIMARY KEY,
+                track TEXT NOT NULL,
+                playlists TEXT NOT NULL,
+                file TEXT,
+                deleted_at TEXT NOT NULL
+            );
+
             CREATE INDEX IF NOT EXISTS idx_tracks_title ON tracks(title);
             CREATE INDEX IF NOT EXISTS idx_tracks_alias ON tracks(alias);
+            CREATE INDEX IF NOT EXISTS idx_aliases_track ON aliases(track_id);
             CREATE INDEX IF NOT EXISTS idx_playlist_tracks_position ON playlist_tracks(playlist_id, position);
             "#,
         ).with_context(|| "Failed to initialize database schema")?;
@@ -80,7 +245,28 @@ impl Database {
                 .parse::<DateTime<Utc>>()
                 .unwrap_or_default(),
             file_path: row.get(6)?,
-            available: row.get::<_, i64>(7)? != 0,
+            file_status: row.get::<_, String>(7)?.parse().unwrap_or_default(),
+            file_checked_at: row
+                .get::<_, Option<String>>(8)?
+                .and_then(|t| t.parse::<DateTime<Utc>>().ok()),
+            source_status: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+            source_checked_at: row
+                .get::<_, Option<String>>(10)?
+                .and_then(|t| t.parse::<DateTime<Utc>>().ok()),
+            format: row.get(11)?,
+            bitrate: row.get(12)?,
+            last_played_at: row
+                .get::<_, Option<String>>(13)?
+                .and_then(|t| t.parse::<DateTime<Utc>>().ok()),
+            remote_only: row.get::<_, i64>(14)? != 0,
+            video_id: row.get(15)?,
+            custom_title: row.get(16)?,
+            artist: row.get(17)?,
+            notes: row.get(18)?,
+            chapters: serde_json::from_str(&row.get::<_, String>(19)?).unwrap_or_default(),
+            trim_start: row.get::<_, Option<i64>>(20)?.map(|t| t as u64),
+            trim_end: row.get::<_, Option<i64>>(21)?.map(|t| t as u64),
+            bookmarks: serde_json::from_str(&row.get::<_, String>(22)?).unwrap_or_default(),
         })
     }
 
@@ -95,11 +281,58 @@ impl Database {
         })
     }
 
+    fn row_to_subscription(row: &Row) -> rusqlite::Result<Subscription> {
+        Ok(Subscription {
+            id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+            url: row.get(1)?,
+            title: row.get(2)?,
+            playlist_id: row
+                .get::<_, Option<String>>(3)?
+                .and_then(|id| id.parse().ok()),
+            filter: row.get(4)?,
+            created_at: row
+                .get::<_, String>(5)?
+                .parse::<DateTime<Utc>>()
+                .unwrap_or_default(),
+            last_synced_at: row
+                .get::<_, Option<String>>(6)?
+                .and_then(|t| t.parse::<DateTime<Utc>>().ok()),
+        })
+    }
+
+    fn row_to_download_job(row: &Row) -> rusqlite::Result<DownloadJob> {
+        Ok(DownloadJob {
+            id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+            url: row.get(1)?,
+            alias: row.get(2)?,
+            playlist: row.get(3)?,
+            status: row
+                .get::<_, String>(4)?
+                .parse()
+                .unwrap_or(JobStatus::Failed),
+            title: row.get(5)?,
+            // Stored as JSON; older rows hold a plain message
+            error: row
+                .get::<_, Option<String>>(6)?
+                .map(|error| serde_json::from_str(&error).unwrap_or(DownloadError::Unknown(error))),
+            track_id: row
+                .get::<_, Option<String>>(7)?
+                .and_then(|id| id.parse().ok()),
+            created_at: row
+                .get::<_, String>(8)?
+                .parse::<DateTime<Utc>>()
+                .unwrap_or_default(),
+            progress: None,
+        })
+    }
+
     // Track operations
     pub fn insert_track(&self, track: &Track) -> Result<()> {
+        if let Some(ref alias) = track.alias {
+            self.check_alias_free(alias, &track.id)?;
+        }
         self.conn.execute(
-            "INSERT INTO tracks (id, url, title, alias, duration, added_at, file_path, available)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            &format!("INSERT INTO tracks ({TRACK_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)"),
             params![
                 track.id.to_string(),
                 track.url,
@@ -108,38 +341,70 @@ impl Database {
                 track.duration as i64,
                 track.added_at.to_rfc3339(),
                 track.file_path,
-                track.available as i64,
+                track.file_status.to_string(),
+                track.file_checked_at.map(|t| t.to_rfc3339()),
+                track.source_status.to_string(),
+                track.source_checked_at.map(|t| t.to_rfc3339()),
+                track.format,
+                track.bitrate,
+                track.last_played_at.map(|t| t.to_rfc3339()),
+                track.remote_only as i64,
+                track.video_id,
+                track.custom_title,
+                track.artist,
+                track.notes,
+                serde_json::to_string(&track.chapters)?,
+                track.trim_start.map(|t| t as i64),
+                track.trim_end.map(|t| t as i64),
+                serde_json::to_string(&track.bookmarks)?,
             ],
         ).with_context(|| "Failed to insert track")?;
+        if let Some(ref alias) = track.alias {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO aliases (alias, track_id) VALUES (?1, ?2)",
+                params![alias, track.id.to_string()],
+            )?;
+        }
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn get_track(&self, id: &Uuid) -> Result<Option<Track>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, url, title, alias, duration, added_at, file_path, available
-             FROM tracks WHERE id = ?1",
-        )?;
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {TRACK_COLUMNS} FROM tracks WHERE id = ?1"))?;
 
         let track = stmt.query_row([id.to_string()], Self::row_to_track).ok();
         Ok(track)
     }
 
     pub fn get_track_by_url(&self, url: &str) -> Result<Option<Track>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, url, title, alias, duration, added_at, file_path, available
-             FROM tracks WHERE url = ?1",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TRACK_COLUMNS} FROM tracks WHERE url = ?1"
+        ))?;
 
         let track = stmt.query_row([url], Self::row_to_track).ok();
         Ok(track)
     }
 
+    /// The track for `url`, matched by video ID where the URL has one so
+    /// other forms of the same video's URL match too.
+    pub fn get_track_by_source(&self, url: &str) -> Result<Option<Track>> {
+        if let Some(video_id) = extract_video_id(url) {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT {TRACK_COLUMNS} FROM tracks WHERE video_id = ?1"
+            ))?;
+            if let Ok(track) = stmt.query_row([video_id], Self::row_to_track) {
+                return Ok(Some(track));
+            }
+        }
+
+        self.get_track_by_url(url)
+    }
+
     pub fn get_all_tracks(&self) -> Result<Vec<Track>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, url, title, alias, duration, added_at, file_path, available
-             FROM tracks ORDER BY added_at DESC",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TRACK_COLUMNS} FROM tracks ORDER BY added_at DESC"
+        ))?;
 
         let tracks = stmt
             .query_map([], Self::row_to_track)?
@@ -152,12 +417,11 @@ impl Database {
     #[allow(dead_code)]
     pub fn search_tracks(&self, query: &str) -> Result<Vec<Track>> {
         let pattern = format!("%{query}%");
-        let mut stmt = self.conn.prepare(
-            "SELECT id, url, title, alias, duration, added_at, file_path, available
-             FROM tracks
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TRACK_COLUMNS} FROM tracks
              WHERE title LIKE ?1 OR alias LIKE ?1
-             ORDER BY added_at DESC",
-        )?;
+             ORDER BY added_at DESC"
+        ))?;
 
         let tracks = stmt
             .query_map([&pattern], Self::row_to_track)?
@@ -167,26 +431,268 @@ impl Database {
         Ok(tracks)
     }
 
-    #[allow(dead_code)]
+    // Alias operations
+
+    /// The track an alias belongs to.
+    pub fn get_track_by_alias(&self, alias: &str) -> Result<Option<Track>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TRACK_COLUMNS} FROM tracks
+             WHERE id = (SELECT track_id FROM aliases WHERE alias = ?1)"
+        ))?;
+
+        let track = stmt.query_row([alias], Self::row_to_track).ok();
+        Ok(track)
+    }
+
+    /// A track's aliases in the order they were added.
+    pub fn get_track_aliases(&self, id: &Uuid) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT alias FROM aliases WHERE track_id = ?1 ORDER BY rowid")?;
+
+        let aliases = stmt
+            .query_map([id.to_string()], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(aliases)
+    }
+
+    pub fn get_all_aliases(&self) -> Result<Vec<TrackAlias>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT alias, track_id FROM aliases ORDER BY rowid")?;
+
+        let aliases = stmt
+            .query_map([], |row| {
+                Ok(TrackAlias {
+                    alias: row.get(0)?,
+                    track_id: Uuid::parse_str(&row.get::<_, String>(1)?).unwrap_or_default(),
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(aliases)
+    }
+
+    fn check_alias_free(&self, alias: &str, id: &Uuid) -> Result<()> {
+        if let Some(other) = self.get_track_by_alias(alias)?
+            && other.id != *id
+        {
+            bail!(
+                "Alias '{alias}' is already used by '{}'",
+                other.display_title()
+            );
+        }
+        Ok(())
+    }
+
+    /// Give a track another alias. The first becomes the one it's shown by.
+    pub fn add_alias(&self, id: &Uuid, alias: &str) -> Result<()> {
+        self.check_alias_free(alias, id)?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO aliases (alias, track_id) VALUES (?1, ?2)",
+            params![alias, id.to_string()],
+        )?;
+        tx.execute(
+            "UPDATE tracks SET alias = ?1 WHERE id = ?2 AND alias IS NULL",
+            params![alias, id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove an alias, returning the track it belonged to. If the track was
+    /// shown by it, its next alias takes over.
+    pub fn remove_alias(&self, alias: &str) -> Result<Track> {
+        let Some(track) = self.get_track_by_alias(alias)? else {
+            bail!("No track has the alias '{alias}'");
+        };
+        let id = track.id.to_string();
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM aliases WHERE alias = ?1", [alias])?;
+        if track.alias.as_deref() == Some(alias) {
+            tx.execute(
+                "UPDATE tracks SET alias =
+                   (SELECT alias FROM aliases WHERE track_id = ?1 ORDER BY rowid LIMIT 1)
+                 WHERE id = ?1",
+                [&id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(track)
+    }
+
+    /// Replace the alias a track is shown by, keeping its other aliases.
+    /// Clearing it shows the next one, if any, as `remove_alias` does.
     pub fn update_track_alias(&self, id: &Uuid, alias: Option<&str>) -> Result<()> {
+        if let Some(alias) = alias {
+            self.check_alias_free(alias, id)?;
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        replace_shown_alias(&tx, &id.to_string(), alias)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Save the fields `mixyt edit` changes.
+    pub fn update_track_metadata(&self, track: &Track) -> Result<()> {
+        if let Some(ref alias) = track.alias {
+            self.check_alias_free(alias, &track.id)?;
+        }
+        let id = track.id.to_string();
+
+        let tx = self.conn.unchecked_transaction()?;
+        replace_shown_alias(&tx, &id, track.alias.as_deref())?;
+        tx.execute(
+            "UPDATE tracks SET custom_title = ?1, artist = ?2, notes = ?3 WHERE id = ?4",
+            params![track.custom_title, track.artist, track.notes, id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn update_track_file_status(
+        &self,
+        id: &Uuid,
+        status: FileStatus,
+        checked_at: DateTime<Utc>,
+    ) -> Result<()> {
         self.conn.execute(
-            "UPDATE tracks SET alias = ?1 WHERE id = ?2",
-            params![alias, id.to_string()],
+            "UPDATE tracks SET file_status = ?1, file_checked_at = ?2 WHERE id = ?3",
+            params![status.to_string(), checked_at.to_rfc3339(), id.to_string()],
         )?;
         Ok(())
     }
 
-    pub fn update_track_availability(&self, id: &Uuid, available: bool) -> Result<()> {
+    pub fn update_track_source_status(
+        &self,
+        id: &Uuid,
+        status: SourceStatus,
+        checked_at: DateTime<Utc>,
+    ) -> Result<()> {
         self.conn.execute(
-            "UPDATE tracks SET available = ?1 WHERE id = ?2",
-            params![available as i64, id.to_string()],
+            "UPDATE tracks SET source_status = ?1, source_checked_at = ?2 WHERE id = ?3",
+            params![status.to_string(), checked_at.to_rfc3339(), id.to_string()],
         )?;
         Ok(())
     }
 
-    pub fn delete_track(&self, id: &Uuid) -> Result<()> {
-        self.conn
-            .execute("DELETE FROM tracks WHERE id = ?1", [id.to_string()])?;
+    pub fn update_track_file_path(&self, id: &Uuid, file_path: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_path = ?1 WHERE id = ?2",
+            params![file_path, id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Point a track at a new local audio file, e.g. after re-encoding it.
+    pub fn update_track_audio(
+        &self,
+        id: &Uuid,
+        file_path: &str,
+        format: Option<&str>,
+        bitrate: Option<u32>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_path = ?1, format = ?2, bitrate = ?3,
+                 remote_only = 0, file_status = 'present'
+             WHERE id = ?4",
+            params![file_path, format, bitrate, id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_track_chapters(&self, id: &Uuid, chapters: &[Chapter]) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET chapters = ?1 WHERE id = ?2",
+            params![serde_json::to_string(chapters)?, id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_track_bookmarks(&self, id: &Uuid, bookmarks: &[Bookmark]) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET bookmarks = ?1 WHERE id = ?2",
+            params![serde_json::to_string(bookmarks)?, id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_track_trim(&self, id: &Uuid, start: Option<u64>, end: Option<u64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET trim_start = ?1, trim_end = ?2 WHERE id = ?3",
+            params![
+                start.map(|t| t as i64),
+                end.map(|t| t as i64),
+                id.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_track_remote_only(&self, id: &Uuid, remote_only: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET remote_only = ?1 WHERE id = ?2",
+            params![remote_only as i64, id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_track_played(&self, id: &Uuid, played_at: DateTime<Utc>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET last_played_at = ?1 WHERE id = ?2",
+            params![played_at.to_rfc3339(), id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Merge a duplicate of a video into `keep`: its playlist entries,
+    /// aliases and download jobs move over, the earliest add and latest play are kept,
+    /// and the duplicate is deleted.
+    pub fn merge_track(&self, keep: &Uuid, duplicate: &Uuid) -> Result<()> {
+        let (Some(kept), Some(dup)) = (self.get_track(keep)?, self.get_track(duplicate)?) else {
+            bail!("Track to merge not found");
+        };
+        let (keep, duplicate) = (keep.to_string(), duplicate.to_string());
+
+        let tx = self.conn.unchecked_transaction()?;
+        // Playlists that already have the kept track drop the duplicate
+        tx.execute(
+            "UPDATE OR IGNORE playlist_tracks SET track_id = ?1 WHERE track_id = ?2",
+            params![keep, duplicate],
+        )?;
+        tx.execute(
+            "DELETE FROM playlist_tracks WHERE track_id = ?1",
+            [&duplicate],
+        )?;
+        tx.execute(
+            "UPDATE download_jobs SET track_id = ?1 WHERE track_id = ?2",
+            params![keep, duplicate],
+        )?;
+        tx.execute(
+            "UPDATE aliases SET track_id = ?1 WHERE track_id = ?2",
+            params![keep, duplicate],
+        )?;
+        tx.execute("DELETE FROM tracks WHERE id = ?1", [&duplicate])?;
+        tx.execute(
+            "UPDATE tracks SET alias = ?1, added_at = ?2, last_played_at = ?3, video_id = ?4
+             WHERE id = ?5",
+            params![
+                kept.alias.or(dup.alias),
+                kept.added_at.min(dup.added_at).to_rfc3339(),
+                kept.last_played_at
+                    .max(dup.last_played_at)
+                    .map(|t| t.to_rfc3339()),
+                kept.video_id.or(dup.video_id),
+                keep,
+            ],
+        )?;
+        tx.commit()?;
+
         Ok(())
     }
 
@@ -205,7 +711,6 @@ impl Database {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn get_playlist(&self, id: &Uuid) -> Result<Option<Playlist>> {
         let mut stmt = self
             .conn
@@ -215,6 +720,16 @@ impl Database {
         Ok(playlist)
     }
 
+    pub fn get_or_create_playlist(&self, name: &str) -> Result<Playlist> {
+        if let Some(existing) = self.get_playlist_by_name(name)? {
+            return Ok(existing);
+        }
+
+        let playlist = Playlist::new(name.to_string());
+        self.insert_playlist(&playlist)?;
+        Ok(playlist)
+    }
+
     pub fn get_playlist_by_name(&self, name: &str) -> Result<Option<Playlist>> {
         let mut stmt = self
             .conn
@@ -245,7 +760,6 @@ impl Database {
     }
 
     // Playlist track operations
-    #[allow(dead_code)]
     pub fn add_track_to_playlist(&self, playlist_id: &Uuid, track_id: &Uuid) -> Result<()> {
         let position: i32 = self.conn.query_row(
             "SELECT COALESCE(MAX(position), -1) + 1 FROM playlist_tracks WHERE playlist_id = ?1",
@@ -261,7 +775,6 @@ impl Database {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn remove_track_from_playlist(&self, playlist_id: &Uuid, track_id: &Uuid) -> Result<()> {
         self.conn.execute(
             "DELETE FROM playlist_tracks WHERE playlist_id = ?1 AND track_id = ?2",
@@ -270,15 +783,13 @@ impl Database {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn get_playlist_tracks(&self, playlist_id: &Uuid) -> Result<Vec<Track>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.url, t.title, t.alias, t.duration, t.added_at, t.file_path, t.available
-             FROM tracks t
-             INNER JOIN playlist_tracks pt ON t.id = pt.track_id
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TRACK_COLUMNS} FROM tracks
+             INNER JOIN playlist_tracks pt ON id = pt.track_id
              WHERE pt.playlist_id = ?1
-             ORDER BY pt.position",
-        )?;
+             ORDER BY pt.position"
+        ))?;
 
         let tracks = stmt
             .query_map([playlist_id.to_string()], Self::row_to_track)?
@@ -294,19 +805,325 @@ impl Database {
             .prepare("SELECT playlist_id, track_id, position FROM playlist_tracks")?;
 
         let entries = stmt
+            .query_map([], Self::row_to_playlist_track)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// The playlist entries of one track.
+    pub fn get_track_playlist_entries(&self, track_id: &Uuid) -> Result<Vec<PlaylistTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT playlist_id, track_id, position FROM playlist_tracks WHERE track_id = ?1",
+        )?;
+
+        let entries = stmt
+            .query_map([track_id.to_string()], Self::row_to_playlist_track)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn row_to_playlist_track(row: &Row) -> rusqlite::Result<PlaylistTrack> {
+        Ok(PlaylistTrack {
+            playlist_id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+            track_id: row.get::<_, String>(1)?.parse().unwrap_or_default(),
+            position: row.get(2)?,
+        })
+    }
+
+    // Trash operations
+
+    /// Move a track into the trash, deleting it from the library.
+    pub fn trash_track(&self, trashed: &TrashedTrack) -> Result<()> {
+        let id = trashed.track.id.to_string();
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO trash (track_id, track, playlists, aliases, file, deleted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                serde_json::to_string(&trashed.track)?,
+                serde_json::to_string(&trashed.playlists)?,
+                serde_json::to_string(&trashed.aliases)?,
+                trashed.file,
+                trashed.deleted_at.to_rfc3339(),
+            ],
+        )?;
+        tx.execute("DELETE FROM playlist_tracks WHERE track_id = ?1", [&id])?;
+        tx.execute("DELETE FROM aliases WHERE track_id = ?1", [&id])?;
+        tx.execute("DELETE FROM tracks WHERE id = ?1", [&id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Put a trashed track back in the library and the playlists it was in,
+    /// at its old positions. Playlists deleted and aliases taken since are
+    /// skipped.
+    pub fn restore_trashed(&self, trashed: &TrashedTrack) -> Result<()> {
+        let mut aliases = Vec::new();
+        for alias in trashed.track.alias.iter().chain(&trashed.aliases) {
+            if self.get_track_by_alias(alias)?.is_none() && !aliases.contains(alias) {
+                aliases.push(alias.clone());
+            }
+        }
+        let track = Track {
+            alias: aliases.first().cloned(),
+            ..trashed.track.clone()
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        self.insert_track(&track)?;
+        for alias in &aliases {
+            tx.execute(
+                "INSERT OR IGNORE INTO aliases (alias, track_id) VALUES (?1, ?2)",
+                params![alias, track.id.to_string()],
+            )?;
+        }
+        for entry in &trashed.playlists {
+            if self.get_playlist(&entry.playlist_id)?.is_none() {
+                continue;
+            }
+            let playlist_id = entry.playlist_id.to_string();
+            tx.execute(
+                "UPDATE playlist_tracks SET position = position + 1
+                 WHERE playlist_id = ?1 AND position >= ?2",
+                params![playlist_id, entry.position],
+            )?;
+            tx.execute(
+                "INSERT INTO playlist_tracks (playlist_id, track_id, position)
+                 VALUES (?1, ?2, ?3)",
+                params![playlist_id, entry.track_id.to_string(), entry.position],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM trash WHERE track_id = ?1",
+            [trashed.track.id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Trashed tracks, most recently removed first.
+    pub fn get_trash(&self) -> Result<Vec<TrashedTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track, playlists, aliases, file, deleted_at FROM trash
+             ORDER BY deleted_at DESC",
+        )?;
+
+        let trash = stmt
             .query_map([], |row| {
-                Ok(PlaylistTrack {
-                    playlist_id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
-                    track_id: row.get::<_, String>(1)?.parse().unwrap_or_default(),
-                    position: row.get(2)?,
-                })
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get(3)?,
+                    row.get::<_, String>(4)?,
+                ))
             })?
             .filter_map(|r| r.ok())
+            .filter_map(|(track, playlists, aliases, file, deleted_at)| {
+                Some(TrashedTrack {
+                    track: serde_json::from_str(&track).ok()?,
+                    playlists: serde_json::from_str(&playlists).unwrap_or_default(),
+                    aliases: serde_json::from_str(&aliases).unwrap_or_default(),
+                    file,
+                    deleted_at: deleted_at.parse().unwrap_or_default(),
+                })
+            })
+            .collect();
+
+        Ok(trash)
+    }
+
+    pub fn delete_trashed(&self, track_id: &Uuid) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM trash WHERE track_id = ?1",
+            [track_id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    // Subscription operations
+    pub fn insert_subscription(&self, subscription: &Subscription) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO subscriptions (id, url, title, playlist_id, filter, created_at, last_synced_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    subscription.id.to_string(),
+                    subscription.url,
+                    subscription.title,
+                    subscription.playlist_id.map(|id| id.to_string()),
+                    subscription.filter,
+                    subscription.created_at.to_rfc3339(),
+                    subscription.last_synced_at.map(|t| t.to_rfc3339()),
+                ],
+            )
+            .with_context(|| "Failed to insert subscription")?;
+        Ok(())
+    }
+
+    pub fn get_subscription_by_url(&self, url: &str) -> Result<Option<Subscription>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, title, playlist_id, filter, created_at, last_synced_at
+             FROM subscriptions WHERE url = ?1",
+        )?;
+
+        let subscription = stmt.query_row([url], Self::row_to_subscription).ok();
+        Ok(subscription)
+    }
+
+    pub fn get_all_subscriptions(&self) -> Result<Vec<Subscription>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, title, playlist_id, filter, created_at, last_synced_at
+             FROM subscriptions ORDER BY title",
+        )?;
+
+        let subscriptions = stmt
+            .query_map([], Self::row_to_subscription)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(subscriptions)
+    }
+
+    pub fn update_subscription_synced(&self, id: &Uuid, synced_at: DateTime<Utc>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE subscriptions SET last_synced_at = ?1 WHERE id = ?2",
+            params![synced_at.to_rfc3339(), id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_subscription(&self, id: &Uuid) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM subscription_entries WHERE subscription_id = ?1",
+            [id.to_string()],
+        )?;
+        self.conn
+            .execute("DELETE FROM subscriptions WHERE id = ?1", [id.to_string()])?;
+        Ok(())
+    }
+
+    /// Record that a playlist or channel entry has been handled by a sync.
+    pub fn add_subscription_entry(&self, subscription_id: &Uuid, video_id: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO subscription_entries (subscription_id, video_id)
+             VALUES (?1, ?2)",
+            params![subscription_id.to_string(), video_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_subscription_entries(&self, subscription_id: &Uuid) -> Result<HashSet<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT video_id FROM subscription_entries WHERE subscription_id = ?1")?;
+
+        let entries = stmt
+            .query_map([subscription_id.to_string()], |row| row.get(0))?
+            .filter_map(|r| r.ok())
             .collect();
 
         Ok(entries)
     }
 
+    // Download job operations
+    pub fn insert_download_job(&self, job: &DownloadJob) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO download_jobs (id, url, alias, playlist, status, title, error, track_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    job.id.to_string(),
+                    job.url,
+                    job.alias,
+                    job.playlist,
+                    job.status.to_string(),
+                    job.title,
+                    job.error.as_ref().map(error_json),
+                    job.track_id.map(|id| id.to_string()),
+                    job.created_at.to_rfc3339(),
+                ],
+            )
+            .with_context(|| "Failed to insert download job")?;
+        Ok(())
+    }
+
+    pub fn update_download_job(&self, job: &DownloadJob) -> Result<()> {
+        self.conn.execute(
+            "UPDATE download_jobs SET status = ?1, title = ?2, error = ?3, track_id = ?4
+             WHERE id = ?5",
+            params![
+                job.status.to_string(),
+                job.title,
+                job.error.as_ref().map(error_json),
+                job.track_id.map(|id| id.to_string()),
+                job.id.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_download_jobs(&self) -> Result<Vec<DownloadJob>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, alias, playlist, status, title, error, track_id, created_at
+             FROM download_jobs ORDER BY created_at",
+        )?;
+
+        let jobs = stmt
+            .query_map([], Self::row_to_download_job)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(jobs)
+    }
+
+    pub fn delete_download_job(&self, id: &Uuid) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM download_jobs WHERE id = ?1", [id.to_string()])?;
+        Ok(())
+    }
+
+    // One-off task operations
+    /// Whether a one-off task outside the database, such as renaming audio
+    /// files, has already run on this library.
+    pub fn is_task_done(&self, name: &str) -> Result<bool> {
+        let done = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM one_off_tasks WHERE name = ?1",
+                [name],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(done.is_some())
+    }
+
+    pub fn mark_task_done(&self, name: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO one_off_tasks (name, done_at) VALUES (?1, ?2)",
+            params![name, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Run SQLite's integrity check, returning the problems it found.
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+
+        let problems = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .filter(|r| r != "ok")
+            .collect();
+
+        Ok(problems)
+    }
+
     #[allow(dead_code)]
     pub fn get_track_count(&self) -> Result<usize> {
         let count: i64 = self
@@ -326,33 +1143,227 @@ impl Database {
     }
 }
 
+fn error_json(error: &DownloadError) -> String {
+    serde_json::to_string(error).unwrap_or_else(|_| error.to_string())
+}
+
+/// Make `alias` the one a track is shown by, in place of its current one.
+/// An unchanged alias is left alone, keeping its place among the others.
+fn replace_shown_alias(conn: &Connection, id: &str, alias: Option<&str>) -> Result<()> {
+    let shown: Option<String> =
+        conn.query_row("SELECT alias FROM tracks WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })?;
+    if shown.as_deref() == alias {
+        return Ok(());
+    }
+
+    conn.execute(
+        "DELETE FROM aliases WHERE track_id = ?1
+         AND alias = (SELECT alias FROM tracks WHERE id = ?1)",
+        [id],
+    )?;
+    match alias {
+        Some(alias) => {
+            conn.execute(
+                "INSERT OR IGNORE INTO aliases (alias, track_id) VALUES (?1, ?2)",
+                params![alias, id],
+            )?;
+            conn.execute(
+                "UPDATE tracks SET alias = ?1 WHERE id = ?2",
+                params![alias, id],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "UPDATE tracks SET alias =
+                   (SELECT alias FROM aliases WHERE track_id = ?1 ORDER BY rowid LIMIT 1)
+                 WHERE id = ?1",
+                [id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn trash(db: &Database, track: &Track) {
+        let trashed = TrashedTrack {
+            track: track.clone(),
+            playlists: Vec::new(),
+            aliases: db.get_track_aliases(&track.id).unwrap(),
+            file: None,
+            deleted_at: Utc::now(),
+        };
+        db.trash_track(&trashed).unwrap();
+    }
+
     #[test]
     fn test_track_crud() {
         let db = Database::open_in_memory().unwrap();
 
-        let track = Track::new(
+        let mut track = Track::new(
             "https://youtube.com/watch?v=test".to_string(),
             "Test Track".to_string(),
             180,
             "/path/to/audio.opus".to_string(),
         );
+        track.format = Some("opus".to_string());
+        track.bitrate = Some(128);
+        track.chapters = vec![
+            Chapter {
+                title: "Intro".to_string(),
+                start: 0,
+                end: 60,
+            },
+            Chapter {
+                title: "Artist - Song".to_string(),
+                start: 60,
+                end: 180,
+            },
+        ];
 
         db.insert_track(&track).unwrap();
 
         let retrieved = db.get_track(&track.id).unwrap().unwrap();
         assert_eq!(retrieved.title, "Test Track");
         assert_eq!(retrieved.url, "https://youtube.com/watch?v=test");
+        assert_eq!(retrieved.format.as_deref(), Some("opus"));
+        assert_eq!(retrieved.bitrate, Some(128));
+        assert_eq!(retrieved.chapters, track.chapters);
+        assert_eq!(retrieved.chapter_at(59), Some(0));
+        assert_eq!(retrieved.chapter_at(60), Some(1));
+
+        db.update_track_trim(&track.id, Some(32), Some(150))
+            .unwrap();
+        let trimmed = db.get_track(&track.id).unwrap().unwrap();
+        assert_eq!((trimmed.play_start(), trimmed.play_end()), (32, 150));
+        assert_eq!(trimmed.format_duration(), "1:58");
+
+        let mut marked = trimmed;
+        marked.set_bookmark("drop", 120);
+        marked.set_bookmark("intro", 10);
+        marked.set_bookmark("drop", 90);
+        db.update_track_bookmarks(&track.id, &marked.bookmarks)
+            .unwrap();
+        let marked = db.get_track(&track.id).unwrap().unwrap();
+        let names: Vec<_> = marked.bookmarks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["intro", "drop"]);
+        assert_eq!(marked.bookmark("DROP").map(|b| b.position), Some(90));
 
         db.update_track_alias(&track.id, Some("my-track")).unwrap();
         let updated = db.get_track(&track.id).unwrap().unwrap();
         assert_eq!(updated.alias, Some("my-track".to_string()));
 
-        db.delete_track(&track.id).unwrap();
+        db.update_track_file_status(&track.id, FileStatus::Missing, Utc::now())
+            .unwrap();
+        db.update_track_source_status(&track.id, SourceStatus::Unavailable, Utc::now())
+            .unwrap();
+        let checked = db.get_track(&track.id).unwrap().unwrap();
+        assert_eq!(checked.file_status, FileStatus::Missing);
+        assert_eq!(checked.source_status, SourceStatus::Unavailable);
+        assert!(checked.file_checked_at.is_some() && checked.source_checked_at.is_some());
+        assert!(!checked.is_playable());
+
+        db.update_track_played(&track.id, Utc::now()).unwrap();
+        db.update_track_remote_only(&track.id, true).unwrap();
+        let evicted = db.get_track(&track.id).unwrap().unwrap();
+        assert!(evicted.last_played_at.is_some());
+        assert!(evicted.remote_only);
+
+        trash(&db, &track);
         assert!(db.get_track(&track.id).unwrap().is_none());
+        assert!(db.integrity_check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let db = Database::open_in_memory().unwrap();
+        let new_track = |url: &str| {
+            let track = Track::new(url.to_string(), url.to_string(), 60, format!("{url}.mp3"));
+            db.insert_track(&track).unwrap();
+            track
+        };
+        let first = new_track("first");
+        let second = new_track("second");
+
+        db.add_alias(&first.id, "focus1").unwrap();
+        db.add_alias(&first.id, "deep").unwrap();
+        assert!(db.add_alias(&second.id, "deep").is_err());
+        assert!(db.update_track_alias(&second.id, Some("focus1")).is_err());
+        assert_eq!(db.get_track_by_alias("deep").unwrap().unwrap().id, first.id);
+        assert_eq!(db.get_track_aliases(&first.id).unwrap(), ["focus1", "deep"]);
+
+        // The first alias is the one shown, until it's removed
+        let shown = |id| db.get_track(id).unwrap().unwrap().alias;
+        assert_eq!(shown(&first.id).as_deref(), Some("focus1"));
+        db.remove_alias("focus1").unwrap();
+        assert_eq!(shown(&first.id).as_deref(), Some("deep"));
+        assert!(db.get_track_by_alias("focus1").unwrap().is_none());
+
+        // Renaming replaces the shown alias only
+        db.add_alias(&first.id, "calm").unwrap();
+        db.update_track_alias(&first.id, Some("night")).unwrap();
+        assert_eq!(db.get_track_aliases(&first.id).unwrap(), ["calm", "night"]);
+
+        // Clearing it falls back to the remaining aliases
+        db.update_track_alias(&first.id, None).unwrap();
+        assert_eq!(shown(&first.id).as_deref(), Some("calm"));
+        db.update_track_alias(&first.id, None).unwrap();
+        assert_eq!(shown(&first.id), None);
+        assert!(db.get_track_aliases(&first.id).unwrap().is_empty());
+        db.add_alias(&first.id, "calm").unwrap();
+
+        // Editing other fields leaves the aliases in their order
+        db.add_alias(&first.id, "dawn").unwrap();
+        let mut edited = db.get_track(&first.id).unwrap().unwrap();
+        edited.notes = Some("rainy".to_string());
+        db.update_track_metadata(&edited).unwrap();
+        assert_eq!(db.get_track_aliases(&first.id).unwrap(), ["calm", "dawn"]);
+        let saved = db.get_track(&first.id).unwrap().unwrap();
+        assert_eq!(saved.alias.as_deref(), Some("calm"));
+        assert_eq!(saved.notes.as_deref(), Some("rainy"));
+
+        trash(&db, &first);
+        assert!(db.get_all_aliases().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_one_off_tasks() {
+        let db = Database::open_in_memory().unwrap();
+        assert!(!db.is_task_done("rename").unwrap());
+        db.mark_task_done("rename").unwrap();
+        db.mark_task_done("rename").unwrap();
+        assert!(db.is_task_done("rename").unwrap());
+        assert!(!db.is_task_done("other").unwrap());
+    }
+
+    #[test]
+    fn test_migrate_availability() {
+        // A library from before the file and source states were split
+        let conn = Connection::open_in_memory().unwrap();
+        let db = Database { conn };
+        db.create_tables().unwrap();
+        for sql in &MIGRATIONS[..3] {
+            db.conn.execute_batch(sql).unwrap();
+        }
+        db.conn
+            .execute_batch(
+                "INSERT INTO tracks (id, url, title, duration, added_at, file_path, available)
+                 VALUES ('00000000-0000-0000-0000-000000000001', 'a', 'A', 1, '', 'a.mp3', 1),
+                        ('00000000-0000-0000-0000-000000000002', 'b', 'B', 1, '', 'b.mp3', 0);
+                 PRAGMA user_version = 3;",
+            )
+            .unwrap();
+
+        db.migrate().unwrap();
+        let track = |url| db.get_track_by_url(url).unwrap().unwrap();
+        assert_eq!(track("a").source_status, SourceStatus::Available);
+        assert_eq!(track("b").source_status, SourceStatus::Unavailable);
+        assert_eq!(track("b").file_status, FileStatus::Present);
     }
 
     #[test]
@@ -386,4 +1397,74 @@ mod tests {
         assert_eq!(tracks[0].title, "Track 1");
         assert_eq!(tracks[1].title, "Track 2");
     }
+
+    #[test]
+    fn test_subscription_operations() {
+        let db = Database::open_in_memory().unwrap();
+
+        let subscription = Subscription::new(
+            "https://www.youtube.com/@mixes/videos".to_string(),
+            "Mixes".to_string(),
+            None,
+            Some("dur:>20m".to_string()),
+        );
+        db.insert_subscription(&subscription).unwrap();
+
+        db.add_subscription_entry(&subscription.id, "abc").unwrap();
+        db.add_subscription_entry(&subscription.id, "abc").unwrap();
+        db.add_subscription_entry(&subscription.id, "def").unwrap();
+        assert_eq!(
+            db.get_subscription_entries(&subscription.id).unwrap().len(),
+            2
+        );
+
+        let now = Utc::now();
+        db.update_subscription_synced(&subscription.id, now)
+            .unwrap();
+        let retrieved = db
+            .get_subscription_by_url(&subscription.url)
+            .unwrap()
+            .unwrap();
+        assert_eq!(retrieved.filter.as_deref(), Some("dur:>20m"));
+        assert_eq!(
+            retrieved.last_synced_at.map(|t| t.timestamp()),
+            Some(now.timestamp())
+        );
+
+        db.delete_subscription(&subscription.id).unwrap();
+        assert!(db.get_all_subscriptions().unwrap().is_empty());
+        assert!(
+            db.get_subscription_entries(&subscription.id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_download_job_operations() {
+        let db = Database::open_in_memory().unwrap();
+
+        let mut job = DownloadJob::new(
+            "https://youtu.be/abc".to_string(),
+            Some("focus".to_string()),
+            None,
+        );
+        db.insert_download_job(&job).unwrap();
+
+        job.status = JobStatus::Failed;
+        job.error = Some(DownloadError::Network("Network unreachable".to_string()));
+        db.update_download_job(&job).unwrap();
+
+        let jobs = db.get_download_jobs().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].status, JobStatus::Failed);
+        assert_eq!(jobs[0].alias.as_deref(), Some("focus"));
+        assert_eq!(
+            jobs[0].error,
+            Some(DownloadError::Network("Network unreachable".to_string()))
+        );
+
+        db.delete_download_job(&job.id).unwrap();
+        assert!(db.get_download_jobs().unwrap().is_empty());
+    }
 }
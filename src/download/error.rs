@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Why a yt-dlp operation failed, classified from its exit code and stderr.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+pub enum DownloadError {
+    #[error("{name} is not installed. Please install it: {url}")]
+    MissingDependency { name: String, url: String },
+
+    #[error("Video is private")]
+    PrivateVideo,
+
+    #[error("Video has been removed or does not exist")]
+    RemovedVideo,
+
+    #[error("Video is not available in your region")]
+    RegionBlocked,
+
+    #[error("Video is age restricted")]
+    AgeRestricted,
+
+    #[error("Video is a live stream or upcoming premiere")]
+    LiveStream,
+
+    #[error("Network error: {0}")]
+    Network(String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("File not found: {0}")]
+    FileNotFound(String),
+
+    #[error("{0}")]
+    Unsupported(String),
+
+    #[error("Download cancelled")]
+    Cancelled,
+
+    #[error("{0}")]
+    Unknown(String),
+}
+
+impl DownloadError {
+    pub fn missing(name: &str) -> Self {
+        let url = match name {
+            "ffmpeg" => "https://ffmpeg.org/download.html",
+            _ => "https://github.com/yt-dlp/yt-dlp#installation",
+        };
+        DownloadError::MissingDependency {
+            name: name.to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    /// Classify a failed yt-dlp run from its exit code and stderr.
+    pub fn from_yt_dlp(exit_code: Option<i32>, stderr: &str) -> Self {
+        let message = error_line(stderr);
+        let lower = stderr.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        if exit_code == Some(2) {
+            return DownloadError::Unknown(format!("Invalid yt-dlp options: {message}"));
+        }
+
+        if has(&["private video"]) {
+            DownloadError::PrivateVideo
+        } else if has(&[
+            "sign in to confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ]) {
+            DownloadError::AgeRestricted
+        } else if has(&[
+            "available in your country",
+            "blocked it in your country",
+            "geo restriction",
+            "geo-restricted",
+        ]) {
+            DownloadError::RegionBlocked
+        } else if has(&[
+            "live event will begin",
+            "premieres in",
+            "is currently live",
+            "this live event",
+            "is a live stream",
+        ]) {
+            DownloadError::LiveStream
+        } else if has(&[
+            "video unavailable",
+            "has been removed",
+            "no longer available",
+            "account associated with this video has been terminated",
+            "does not exist",
+            "http error 404",
+        ]) {
+            DownloadError::RemovedVideo
+        } else if has(&[
+            "http error 429",
+            "too many requests",
+            "rate-limited",
+            "rate limited",
+            "confirm you're not a bot",
+        ]) {
+            DownloadError::RateLimited(message)
+        } else if has(&[
+            "timed out",
+            "connection reset",
+            "connection refused",
+            "connection aborted",
+            "network is unreachable",
+            "temporary failure in name resolution",
+            "name or service not known",
+            "getaddrinfo failed",
+            "incompleteread",
+            "remote end closed connection",
+            "http error 500",
+            "http error 502",
+            "http error 503",
+            "http error 504",
+        ]) {
+            DownloadError::Network(message)
+        } else {
+            DownloadError::Unknown(format!("yt-dlp failed: {message}"))
+        }
+    }
+
+    /// Whether the failure may go away on its own, so retrying later makes sense.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DownloadError::Network(_) | DownloadError::RateLimited(_)
+        )
+    }
+
+    /// Whether the failure means the source video itself can't be downloaded.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            DownloadError::PrivateVideo
+                | DownloadError::RemovedVideo
+                | DownloadError::RegionBlocked
+                | DownloadError::AgeRestricted
+                | DownloadError::FileNotFound(_)
+        )
+    }
+
+    /// A suggestion for the user, where there is something they can do.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            DownloadError::AgeRestricted => Some(
+                "Age restricted videos need cookies, e.g. --cookies-from-browser in your yt-dlp config",
+            ),
+            DownloadError::LiveStream => Some("Try again once the stream has ended"),
+            DownloadError::Network(_) => Some("Check your internet connection and try again"),
+            DownloadError::RateLimited(_) => Some("Wait a while before downloading more"),
+            DownloadError::Unknown(message) if message.starts_with("yt-dlp") => {
+                Some("Updating yt-dlp often fixes this: yt-dlp -U")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The most relevant line of yt-dlp's stderr: the last `ERROR:` line if any.
+fn error_line(stderr: &str) -> String {
+    let stderr = stderr.trim();
+    stderr
+        .lines()
+        .rev()
+        .find(|line| line.starts_with("ERROR:"))
+        .or_else(|| stderr.lines().last())
+        .unwrap_or("unknown error")
+        .trim_start_matches("ERROR:")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_yt_dlp_errors() {
+        let classify = |stderr| DownloadError::from_yt_dlp(Some(1), stderr);
+
+        assert_eq!(
+            classify("ERROR: [youtube] abc: Private video. Sign in if you've been granted access"),
+            DownloadError::PrivateVideo
+        );
+        assert_eq!(
+            classify(
+                "ERROR: [youtube] abc: Video unavailable. This video has been removed by the uploader"
+            ),
+            DownloadError::RemovedVideo
+        );
+        assert_eq!(
+            classify(
+                "ERROR: [youtube] abc: The uploader has not made this video available in your country"
+            ),
+            DownloadError::RegionBlocked
+        );
+        assert_eq!(
+            classify(
+                "ERROR: [youtube] abc: Video unavailable. The uploader has blocked it in your country"
+            ),
+            DownloadError::RegionBlocked
+        );
+        assert_eq!(
+            classify("ERROR: [youtube] abc: Video unavailable"),
+            DownloadError::RemovedVideo
+        );
+        assert_eq!(
+            classify(
+                "ERROR: [youtube] abc: Sign in to confirm your age. This video may be inappropriate for some users."
+            ),
+            DownloadError::AgeRestricted
+        );
+        assert_eq!(
+            classify("ERROR: [youtube] abc: This live event will begin in 3 hours."),
+            DownloadError::LiveStream
+        );
+        assert_eq!(
+            classify(
+                "WARNING: retrying\nERROR: unable to download video data: HTTP Error 429: Too Many Requests"
+            ),
+            DownloadError::RateLimited(
+                "unable to download video data: HTTP Error 429: Too Many Requests".to_string()
+            )
+        );
+        assert_eq!(
+            classify("ERROR: Unable to download webpage: <urlopen error [Errno -3] Temporary failure in name resolution>"),
+            DownloadError::Network(
+                "Unable to download webpage: <urlopen error [Errno -3] Temporary failure in name resolution>"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(DownloadError::Network("timed out".to_string()).is_transient());
+        assert!(DownloadError::RateLimited("429".to_string()).is_transient());
+        assert!(!DownloadError::PrivateVideo.is_transient());
+        assert!(DownloadError::PrivateVideo.is_unavailable());
+        assert!(!DownloadError::Network("timed out".to_string()).is_unavailable());
+    }
+}
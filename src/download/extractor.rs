@@ -0,0 +1,65 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use super::{DownloadError, DownloadPhase, PlaylistInfo};
+use crate::models::Chapter;
+
+/// Metadata for a single downloadable item.
+#[derive(Debug, Clone)]
+pub struct MediaInfo {
+    /// The source's own ID for the item (e.g. the YouTube video ID), if any
+    pub id: Option<String>,
+    pub title: String,
+    /// Canonical URL, used to detect duplicates
+    pub url: String,
+    /// Duration in seconds, 0 if unknown
+    pub duration: u64,
+    pub chapters: Vec<Chapter>,
+}
+
+/// A source of audio: resolves metadata for a URL and fetches it to disk.
+///
+/// The `Downloader` picks an extractor per URL (see `download.extractors` in
+/// the config) and wraps it with retries and track bookkeeping.
+pub trait Extractor: Send + Sync {
+    /// Name used to select this extractor in the config
+    fn name(&self) -> &'static str;
+
+    /// Check that any external tools this extractor needs are installed.
+    fn check(&self) -> Result<(), DownloadError> {
+        Ok(())
+    }
+
+    fn info(&self, url: &str) -> Result<MediaInfo, DownloadError>;
+
+    /// Enumerate a playlist or channel without downloading it.
+    fn playlist(&self, url: &str) -> Result<PlaylistInfo, DownloadError> {
+        Err(DownloadError::Unsupported(format!(
+            "The {} extractor can't list playlists: {url}",
+            self.name()
+        )))
+    }
+
+    /// Fetch the audio for `url` to `output` (a path without extension),
+    /// returning the path of the file written. Should stop once `cancel` is set.
+    fn download(
+        &self,
+        url: &str,
+        output: &Path,
+        cancel: &AtomicBool,
+        on_progress: &dyn Fn(DownloadPhase),
+    ) -> Result<PathBuf, DownloadError>;
+
+    /// Open the audio for `url` as a byte stream in its source format, so it
+    /// can be played while it downloads.
+    fn stream(&self, url: &str) -> Result<Box<dyn Read + Send>, DownloadError> {
+        Err(DownloadError::Unsupported(format!(
+            "The {} extractor can't stream: {url}",
+            self.name()
+        )))
+    }
+
+    /// Check whether `url` can still be downloaded, without downloading it.
+    fn check_availability(&self, url: &str) -> Result<(), DownloadError>;
+}
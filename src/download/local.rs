@@ -0,0 +1,82 @@
+use rodio::{Decoder, Source};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use super::{DownloadError, DownloadPhase, Extractor, MediaInfo};
+
+/// Imports audio files that are already on disk, given as `file://` URLs or
+/// plain paths. The file is copied into the library unchanged.
+pub struct LocalFileExtractor;
+
+impl LocalFileExtractor {
+    fn resolve(url: &str) -> Result<PathBuf, DownloadError> {
+        let path = Path::new(url.strip_prefix("file://").unwrap_or(url));
+        path.canonicalize()
+            .map_err(|_| DownloadError::FileNotFound(path.display().to_string()))
+    }
+}
+
+impl Extractor for LocalFileExtractor {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn info(&self, url: &str) -> Result<MediaInfo, DownloadError> {
+        let path = Self::resolve(url)?;
+
+        let title = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        // Not every format reports a duration up front
+        let duration = File::open(&path)
+            .ok()
+            .and_then(|file| Decoder::new(BufReader::new(file)).ok())
+            .and_then(|decoder| decoder.total_duration())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Ok(MediaInfo {
+            id: None,
+            title,
+            url: format!("file://{}", path.display()),
+            duration,
+            chapters: Vec::new(),
+        })
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        output: &Path,
+        _cancel: &AtomicBool,
+        _on_progress: &dyn Fn(DownloadPhase),
+    ) -> Result<PathBuf, DownloadError> {
+        let source = Self::resolve(url)?;
+        let target = match source.extension() {
+            Some(ext) => PathBuf::from(format!("{}.{}", output.display(), ext.to_string_lossy())),
+            None => output.to_path_buf(),
+        };
+
+        fs::copy(&source, &target).map_err(|e| {
+            DownloadError::Unknown(format!("Failed to copy {}: {e}", source.display()))
+        })?;
+
+        Ok(target)
+    }
+
+    fn stream(&self, url: &str) -> Result<Box<dyn Read + Send>, DownloadError> {
+        let source = Self::resolve(url)?;
+        let file = File::open(&source).map_err(|e| {
+            DownloadError::Unknown(format!("Failed to open {}: {e}", source.display()))
+        })?;
+        Ok(Box::new(file))
+    }
+
+    fn check_availability(&self, url: &str) -> Result<(), DownloadError> {
+        Self::resolve(url).map(|_| ())
+    }
+}
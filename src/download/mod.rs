@@ -1,188 +1,373 @@
-use anyhow::{Context, Result, bail};
-use serde::Deserialize;
-use std::io::{BufRead, BufReader, Read as _};
+mod error;
+mod extractor;
+mod local;
+mod ytdlp;
+
+pub use error::DownloadError;
+pub use extractor::{Extractor, MediaInfo};
+pub use local::LocalFileExtractor;
+pub use ytdlp::YtDlpExtractor;
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
 
 use crate::config::Config;
-use crate::models::Track;
+use crate::convert::{Target, convert_file};
+use crate::models::{Chapter, Track};
 
 pub enum DownloadPhase {
-    Downloading { percent: f64, speed: String, eta: String },
+    Downloading {
+        percent: f64,
+        speed: String,
+        eta: String,
+    },
     Converting,
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        delay_secs: u64,
+        reason: String,
+    },
 }
 
-#[derive(Debug, Deserialize)]
-struct YtDlpInfo {
-    #[allow(dead_code)]
-    id: String,
-    title: String,
-    duration: Option<f64>,
-    webpage_url: String,
+impl std::fmt::Display for DownloadPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadPhase::Downloading {
+                percent,
+                speed,
+                eta,
+            } => write!(f, "{percent:5.1}% {speed} ETA {eta}"),
+            DownloadPhase::Converting => write!(f, "Converting..."),
+            DownloadPhase::Retrying {
+                attempt,
+                max_attempts,
+                delay_secs,
+                ..
+            } => write!(
+                f,
+                "Attempt {attempt}/{max_attempts} failed, retrying in {delay_secs}s"
+            ),
+        }
+    }
+}
+
+/// A single video listed in a YouTube playlist or channel.
+#[derive(Debug, Clone)]
+pub struct PlaylistEntry {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub duration: Option<u64>,
+}
+
+/// The flat listing of a YouTube playlist or channel.
+#[derive(Debug, Clone)]
+pub struct PlaylistInfo {
+    pub title: String,
+    pub entries: Vec<PlaylistEntry>,
 }
 
 pub struct Downloader {
     config: Config,
+    extractors: Vec<Box<dyn Extractor>>,
 }
 
 impl Downloader {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let extractors: Vec<Box<dyn Extractor>> = vec![
+            Box::new(YtDlpExtractor::new(&config)),
+            Box::new(LocalFileExtractor),
+        ];
+        Self::with_extractors(config, extractors)
     }
 
-    pub fn check_dependencies() -> Result<()> {
-        // Check yt-dlp
-        let yt_dlp = Command::new("yt-dlp").arg("--version").output();
+    pub fn with_extractors(config: Config, extractors: Vec<Box<dyn Extractor>>) -> Self {
+        Self { config, extractors }
+    }
 
-        if yt_dlp.is_err() {
-            bail!(
-                "yt-dlp is not installed. Please install it: https://github.com/yt-dlp/yt-dlp#installation"
-            );
-        }
+    /// Pick the extractor for a URL: `local` for `file://` URLs and paths,
+    /// otherwise the one configured for the host in `download.extractors`,
+    /// falling back to yt-dlp.
+    pub fn extractor_for(&self, url: &str) -> Result<&dyn Extractor, DownloadError> {
+        let name = match url.split_once("://") {
+            None | Some(("file", _)) => "local",
+            Some(("http" | "https", rest)) => {
+                let host = rest
+                    .split(['/', '?', '#'])
+                    .next()
+                    .unwrap_or_default()
+                    .split(':')
+                    .next()
+                    .unwrap_or_default();
+                self.config
+                    .download
+                    .extractors
+                    .iter()
+                    .find(|(domain, _)| {
+                        host == domain.as_str() || host.ends_with(&format!(".{domain}"))
+                    })
+                    .map(|(_, name)| name.as_str())
+                    .unwrap_or("yt-dlp")
+            }
+            Some((scheme, _)) => {
+                return Err(DownloadError::Unsupported(format!(
+                    "Unsupported URL scheme '{scheme}'"
+                )));
+            }
+        };
 
-        // Check ffmpeg
-        let ffmpeg = Command::new("ffmpeg").arg("-version").output();
+        self.extractors
+            .iter()
+            .find(|e| e.name() == name)
+            .map(|e| e.as_ref())
+            .ok_or_else(|| DownloadError::Unsupported(format!("Unknown extractor '{name}'")))
+    }
 
-        if ffmpeg.is_err() {
-            bail!("ffmpeg is not installed. Please install it: https://ffmpeg.org/download.html");
+    /// Check that the tools needed to download `urls` are installed.
+    pub fn check_dependencies<'a>(
+        &self,
+        urls: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), DownloadError> {
+        let mut checked = HashSet::new();
+        for url in urls {
+            let extractor = self.extractor_for(url)?;
+            if checked.insert(extractor.name()) {
+                extractor.check()?;
+            }
         }
-
         Ok(())
     }
 
-    pub fn get_video_info(&self, url: &str) -> Result<(String, String, u64)> {
-        let output = Command::new("yt-dlp")
-            .args(["--dump-json", "--no-download", "--no-playlist", url])
-            .output()
-            .with_context(|| "Failed to run yt-dlp")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            bail!("yt-dlp failed: {stderr}");
-        }
+    pub fn get_video_info(&self, url: &str) -> Result<(String, String, u64), DownloadError> {
+        let info = self.extractor_for(url)?.info(url)?;
+        Ok((info.title, info.url, info.duration))
+    }
 
-        let info: YtDlpInfo = serde_json::from_slice(&output.stdout)
-            .with_context(|| "Failed to parse yt-dlp output")?;
+    pub fn get_chapters(&self, url: &str) -> Result<Vec<Chapter>, DownloadError> {
+        Ok(self.extractor_for(url)?.info(url)?.chapters)
+    }
 
-        let duration = info.duration.unwrap_or(0.0) as u64;
-        Ok((info.title, info.webpage_url, duration))
+    /// Enumerate the entries of a playlist or channel without downloading them.
+    pub fn get_playlist_info(&self, url: &str) -> Result<PlaylistInfo, DownloadError> {
+        self.extractor_for(url)?.playlist(url)
     }
 
-    pub fn download(&self, url: &str, on_progress: impl Fn(DownloadPhase)) -> Result<Track> {
-        let (title, canonical_url, duration) = self.get_video_info(url)?;
+    /// Download `url` as a new track. `start` is the position the user's
+    /// URL pointed at (see `extract_start_time`), saved as a bookmark; the
+    /// canonical URL passed here no longer has it.
+    pub fn download(
+        &self,
+        url: &str,
+        start: Option<u64>,
+        on_progress: impl Fn(DownloadPhase),
+    ) -> Result<Track, DownloadError> {
+        self.download_cancellable(url, start, &AtomicBool::new(false), on_progress)
+    }
 
-        let audio_dir = self.config.audio_dir();
-        let format = &self.config.audio.format;
+    /// Like `download`, but stops the extractor once `cancel` is set.
+    pub fn download_cancellable(
+        &self,
+        url: &str,
+        start: Option<u64>,
+        cancel: &AtomicBool,
+        on_progress: impl Fn(DownloadPhase),
+    ) -> Result<Track, DownloadError> {
+        let extractor = self.extractor_for(url)?;
+        let info = self.with_retries(cancel, &on_progress, || extractor.info(url))?;
+
+        // Name the file after the source ID, or the track's own ID, so
+        // videos with the same title don't overwrite each other
+        let mut track = Track::new(info.url, info.title, info.duration, String::new());
+        let key = audio_file_key(info.id.as_deref(), &track);
+        let output = self
+            .config
+            .audio_dir()
+            .join(audio_file_stem(&track.title, &key));
+
+        let file_path = self.with_retries(cancel, &on_progress, || {
+            extractor.download(&track.url, &output, cancel, &on_progress)
+        })?;
+
+        self.set_audio_file(&mut track, &file_path);
+        track.chapters = info.chapters;
+        if let Some(start) = start {
+            track.set_bookmark(START_BOOKMARK, start);
+        }
+        Ok(track)
+    }
 
-        // Generate a safe filename
-        let safe_title: String = title
-            .chars()
-            .map(|c| {
-                if c.is_alphanumeric() || c == ' ' || c == '-' {
-                    c
-                } else {
-                    '_'
-                }
-            })
-            .collect();
-        let safe_title = safe_title.trim();
+    /// Move a finished `stream` of `track` into the library, named and
+    /// encoded the way a download of it would be.
+    pub fn save_stream(&self, mut track: Track, stream: &Path) -> Result<Track, DownloadError> {
+        let info = self.extractor_for(&track.url)?.info(&track.url)?;
+        let key = audio_file_key(info.id.as_deref(), &track);
+        let output = self
+            .config
+            .audio_dir()
+            .join(audio_file_stem(&track.title, &key));
+        let failed = |e: &dyn std::fmt::Display| {
+            DownloadError::Unknown(format!("Failed to save the stream: {e}"))
+        };
+
+        // Streams are MP3 so they decode while they grow, which is also what
+        // keeping the original gives here
+        let audio = &self.config.audio;
+        let (file, extension) = if audio.keeps_original() || audio.format == "mp3" {
+            (stream.to_path_buf(), "mp3")
+        } else {
+            let target = Target::new(&audio.format, None).map_err(|e| failed(&e))?;
+            let converted = convert_file(stream, &target).map_err(|e| failed(&e))?;
+            let _ = std::fs::remove_file(stream);
+            (converted.path, target.extension)
+        };
+
+        let file_path = PathBuf::from(format!("{}.{extension}", output.display()));
+        std::fs::rename(&file, &file_path).map_err(|e| failed(&e))?;
+
+        self.set_audio_file(&mut track, &file_path);
+        track.chapters = info.chapters;
+        Ok(track)
+    }
 
-        let output_template = audio_dir.join(format!("{safe_title}.%(ext)s"));
+    /// Record where `track`'s audio was stored, and its format.
+    fn set_audio_file(&self, track: &mut Track, file_path: &Path) {
+        track.file_path = self.config.relative_audio_path(file_path);
+        track.format = file_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        track.bitrate = probe_bitrate(file_path);
+    }
 
-        let mut child = Command::new("yt-dlp")
-            .args([
-                "-x", // Extract audio
-                "--audio-format",
-                format,
-                "--audio-quality",
-                "0", // Best quality
-                "--no-playlist",
-                "--progress",
-                "--newline",
-                "--progress-template",
-                "download:PROGRESS:%(progress._percent_str)s:%(progress._speed_str)s:%(progress._eta_str)s",
-                "--progress-template",
-                "postprocess:POSTPROCESS",
-                "-o",
-                output_template.to_str().unwrap(),
-                "--print",
-                "after_move:filepath",
-                &canonical_url,
-            ])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .with_context(|| "Failed to run yt-dlp")?;
+    /// Run `attempt`, retrying transient (network-class) failures with
+    /// exponential backoff as configured in `download.retries`.
+    fn with_retries<T>(
+        &self,
+        cancel: &AtomicBool,
+        on_progress: &impl Fn(DownloadPhase),
+        mut attempt: impl FnMut() -> Result<T, DownloadError>,
+    ) -> Result<T, DownloadError> {
+        let retries = &self.config.download.retries;
+        let max_attempts = retries.attempts.max(1);
+
+        for n in 1.. {
+            debug!("Download attempt {n}/{max_attempts}");
+
+            let error = match attempt() {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
 
-        let stderr = child.stderr.take().unwrap();
-        let reader = BufReader::new(stderr);
-        let mut stderr_output = String::new();
+            let reason = error.to_string();
 
-        for line in reader.lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(_) => continue,
-            };
+            if n >= max_attempts || cancel.load(Ordering::SeqCst) || !error.is_transient() {
+                if n > 1 {
+                    warn!("Attempt {n}/{max_attempts} failed, giving up: {reason}");
+                }
+                return Err(error);
+            }
 
-            if let Some(rest) = line.strip_prefix("PROGRESS:") {
-                let parts: Vec<&str> = rest.splitn(3, ':').collect();
-                if parts.len() == 3 {
-                    let percent = parts[0]
-                        .trim()
-                        .trim_end_matches('%')
-                        .parse::<f64>()
-                        .unwrap_or(0.0);
-                    let speed = parts[1].trim().to_string();
-                    let eta = parts[2].trim().to_string();
-                    on_progress(DownloadPhase::Downloading { percent, speed, eta });
+            let delay = retries.delay_for(n);
+            warn!(
+                "Attempt {n}/{max_attempts} failed: {reason}. Retrying in {}s",
+                delay.as_secs()
+            );
+            on_progress(DownloadPhase::Retrying {
+                attempt: n,
+                max_attempts,
+                delay_secs: delay.as_secs(),
+                reason,
+            });
+
+            let deadline = Instant::now() + delay;
+            while Instant::now() < deadline {
+                if cancel.load(Ordering::SeqCst) {
+                    return Err(error);
                 }
-            } else if line.starts_with("POSTPROCESS") {
-                on_progress(DownloadPhase::Converting);
-            } else {
-                stderr_output.push_str(&line);
-                stderr_output.push('\n');
+                thread::sleep(Duration::from_millis(100));
             }
         }
 
-        // stderr EOF — process has finished writing, read stdout and wait
-        let mut stdout = child.stdout.take().unwrap();
-        let mut stdout_str = String::new();
-        stdout
-            .read_to_string(&mut stdout_str)
-            .with_context(|| "Failed to read yt-dlp output")?;
+        unreachable!("retry loop only exits by returning")
+    }
 
-        let status = child.wait().with_context(|| "yt-dlp process failed")?;
+    /// Download `url` into `output` as MP3, a format the player can decode
+    /// while the file is still being written. Stops once `cancel` is set.
+    pub fn stream(
+        &self,
+        url: &str,
+        output: &Path,
+        cancel: &AtomicBool,
+    ) -> Result<(), DownloadError> {
+        let mut source = self.extractor_for(url)?.stream(url)?;
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-v",
+                "error",
+                "-i",
+                "pipe:0",
+                "-vn",
+                "-c:a",
+                "libmp3lame",
+                "-f",
+                "mp3",
+            ])
+            .arg(output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => DownloadError::missing("ffmpeg"),
+                _ => DownloadError::Unknown(format!("Failed to run ffmpeg: {e}")),
+            })?;
+
+        let mut stdin = ffmpeg.stdin.take().unwrap();
+        let mut buf = [0u8; 64 * 1024];
+        let copied = loop {
+            if cancel.load(Ordering::SeqCst) {
+                break Err(DownloadError::Cancelled);
+            }
+            match source.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(n) => {
+                    if let Err(e) = stdin.write_all(&buf[..n]) {
+                        break Err(DownloadError::Unknown(format!("ffmpeg stopped early: {e}")));
+                    }
+                }
+                Err(e) => break Err(read_error(e)),
+            }
+        };
 
-        if !status.success() {
-            bail!("Download failed: {}", stderr_output.trim());
+        drop(stdin);
+        if copied.is_err() {
+            let _ = ffmpeg.kill();
         }
+        let status = ffmpeg
+            .wait()
+            .map_err(|e| DownloadError::Unknown(format!("ffmpeg process failed: {e}")))?;
+        copied?;
 
-        let file_path = stdout_str.trim().to_string();
-
-        if file_path.is_empty() || !Path::new(&file_path).exists() {
-            // Try to find the file
-            let expected_path = audio_dir.join(format!("{safe_title}.{format}"));
-            if expected_path.exists() {
-                return Ok(Track::new(
-                    canonical_url,
-                    title,
-                    duration,
-                    expected_path.to_string_lossy().to_string(),
-                ));
-            }
-            bail!("Download completed but file not found");
+        if !status.success() {
+            return Err(DownloadError::Unknown(
+                "ffmpeg failed to encode the stream".to_string(),
+            ));
         }
 
-        Ok(Track::new(canonical_url, title, duration, file_path))
+        Ok(())
     }
 
-    pub fn check_availability(&self, url: &str) -> Result<bool> {
-        let output = Command::new("yt-dlp")
-            .args(["--simulate", "--no-playlist", url])
-            .output()
-            .with_context(|| "Failed to check video availability")?;
-
-        Ok(output.status.success())
+    /// Check whether a video can still be downloaded, without downloading it.
+    pub fn check_availability(&self, url: &str) -> Result<(), DownloadError> {
+        self.extractor_for(url)?.check_availability(url)
     }
 
     #[allow(dead_code)]
@@ -191,26 +376,332 @@ impl Downloader {
     }
 }
 
-#[allow(dead_code)]
+/// The error behind a failed read of an extractor's stream: the extractor's
+/// own if it failed, otherwise a network error.
+fn read_error(e: io::Error) -> DownloadError {
+    let message = e.to_string();
+    match e
+        .into_inner()
+        .map(|inner| inner.downcast::<DownloadError>())
+    {
+        Some(Ok(error)) => *error,
+        _ => DownloadError::Network(message),
+    }
+}
+
+/// Read an audio file's bitrate in kbps with ffprobe, if available.
+pub fn probe_bitrate(path: &Path) -> Option<u32> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=bit_rate",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(path)
+        .output()
+        .ok()?;
+
+    let bps: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some((bps / 1000) as u32)
+}
+
+/// File name (without extension) for a track's audio: `<title> [<key>]`,
+/// where the key is unique per track and the title is just for readability.
+pub fn audio_file_stem(title: &str, key: &str) -> String {
+    format!("{}{}]", audio_file_prefix(title), safe_file_name(key))
+}
+
+/// The start of the name of every file a download of `title` writes,
+/// partial ones included.
+pub fn audio_file_prefix(title: &str) -> String {
+    format!("{} [", safe_file_name(title).trim())
+}
+
+fn safe_file_name(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Key for `audio_file_stem`: the source's media ID (the video ID on
+/// YouTube), or the track's own ID for sources without one.
+pub fn audio_file_key(media_id: Option<&str>, track: &Track) -> String {
+    media_id.map_or_else(|| track.id.to_string(), str::to_string)
+}
+
+/// Turn a local path into an absolute `file://` URL, so it still resolves
+/// when handed to the daemon. Other URLs are returned unchanged.
+pub fn resolve_source(url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string();
+    }
+
+    match std::fs::canonicalize(url) {
+        Ok(path) => format!("file://{}", path.display()),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Whether a URL points at a YouTube playlist or channel rather than a single video.
+pub fn is_collection_url(url: &str) -> bool {
+    if !url.contains("youtube.com") {
+        return false;
+    }
+
+    let has_video = url.contains("/watch") && url.contains("v=");
+    let has_list = url.split(['?', '&']).any(|s| s.starts_with("list="));
+
+    (has_list && !has_video)
+        || url.contains("/playlist")
+        || ["/@", "/channel/", "/c/", "/user/"]
+            .iter()
+            .any(|prefix| url.contains(prefix))
+}
+
+/// Point bare channel URLs at their uploads tab, so yt-dlp lists videos
+/// instead of the channel's tabs.
+fn normalize_collection_url(url: &str) -> String {
+    let base = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(url)
+        .trim_end_matches('/');
+    let segments: Vec<&str> = base.split('/').collect();
+
+    let channel_root = match segments.iter().position(|s| s.contains("youtube.com")) {
+        Some(host) => {
+            let rest = &segments[host + 1..];
+            match rest {
+                [handle] => handle.starts_with('@'),
+                [kind, _] => matches!(*kind, "channel" | "c" | "user"),
+                _ => false,
+            }
+        }
+        None => false,
+    };
+
+    if channel_root {
+        format!("{base}/videos")
+    } else {
+        url.to_string()
+    }
+}
+
+/// The YouTube video ID in a URL, in any of the forms YouTube links take:
+/// watch pages on www, m. and music., youtu.be, shorts, live streams, embeds
+/// and youtube-nocookie.
 pub fn extract_video_id(url: &str) -> Option<String> {
-    // Handle various YouTube URL formats
-    if url.contains("youtu.be/") {
-        url.split("youtu.be/")
-            .nth(1)
-            .and_then(|s| s.split(['?', '&']).next())
-            .map(|s| s.to_string())
-    } else if url.contains("youtube.com") {
-        url.split(['?', '&'])
-            .find(|s| s.starts_with("v="))
-            .map(|s| s[2..].to_string())
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.to_lowercase();
+    let is_host = |domain: &str| host == domain || host.ends_with(&format!(".{domain}"));
+
+    let mut segments = path.split('/');
+    let id = if host == "youtu.be" {
+        segments.next()
+    } else if is_host("youtube.com") || is_host("youtube-nocookie.com") {
+        match segments.next() {
+            Some("watch") => query.split('&').find_map(|p| p.strip_prefix("v=")),
+            Some("shorts" | "live" | "embed" | "v" | "e") => segments.next(),
+            _ => None,
+        }
     } else {
         None
+    }?;
+
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_string())
+}
+
+/// Name of the bookmark made from a URL's start time.
+pub const START_BOOKMARK: &str = "start";
+
+/// The start time in a URL, e.g. `?t=90`, `&t=1m30s` or `#t=90`, in seconds.
+pub fn extract_start_time(url: &str) -> Option<u64> {
+    let (_, params) = url.split_once(['?', '#'])?;
+    let value = params
+        .split(['&', '#'])
+        .find_map(|p| p.strip_prefix("t=").or_else(|| p.strip_prefix("start=")))?;
+
+    if let Ok(seconds) = value.trim_end_matches('s').parse() {
+        return Some(seconds);
+    }
+
+    // 1h2m3s
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                let part = number.parse::<u64>().ok()?.checked_mul(unit)?;
+                seconds = part.checked_add(seconds)?;
+                number.clear();
+            }
+            _ => return None,
+        }
     }
+    number.is_empty().then_some(seconds)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    /// Serves one canned video, failing the first `failures` downloads with a
+    /// network error.
+    struct FakeExtractor {
+        failures: u32,
+        attempts: AtomicU32,
+    }
+
+    impl Extractor for FakeExtractor {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn info(&self, _url: &str) -> Result<MediaInfo, DownloadError> {
+            Ok(MediaInfo {
+                id: Some("mix1".to_string()),
+                title: "Fake Mix".to_string(),
+                url: "https://fake.example/mix".to_string(),
+                duration: 60,
+                chapters: Vec::new(),
+            })
+        }
+
+        fn download(
+            &self,
+            _url: &str,
+            output: &Path,
+            _cancel: &AtomicBool,
+            _on_progress: &dyn Fn(DownloadPhase),
+        ) -> Result<PathBuf, DownloadError> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(DownloadError::Network("Connection reset".to_string()));
+            }
+            let path = output.with_extension("mp3");
+            std::fs::write(&path, b"audio").unwrap();
+            Ok(path)
+        }
+
+        fn check_availability(&self, _url: &str) -> Result<(), DownloadError> {
+            Ok(())
+        }
+    }
+
+    fn fake_downloader(dir: &Path, failures: u32) -> Downloader {
+        let mut config = Config::default();
+        config.storage.path = dir.to_path_buf();
+        config.download.retries.backoff = 0;
+        config
+            .download
+            .extractors
+            .insert("fake.example".to_string(), "fake".to_string());
+        std::fs::create_dir_all(config.audio_dir()).unwrap();
+
+        let extractors: Vec<Box<dyn Extractor>> = vec![
+            Box::new(YtDlpExtractor::new(&config)),
+            Box::new(LocalFileExtractor),
+            Box::new(FakeExtractor {
+                failures,
+                attempts: AtomicU32::new(0),
+            }),
+        ];
+        Downloader::with_extractors(config, extractors)
+    }
+
+    #[test]
+    fn test_audio_file_stem() {
+        assert_eq!(
+            audio_file_stem("Lofi Mix: 24/7", "dQw4w9WgXcQ"),
+            "Lofi Mix_ 24_7 [dQw4w9WgXcQ]"
+        );
+        assert_ne!(
+            audio_file_stem("Lofi Mix", "aaa"),
+            audio_file_stem("Lofi Mix", "bbb")
+        );
+    }
+
+    #[test]
+    fn test_extractor_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = fake_downloader(dir.path(), 0);
+        let name = |url| downloader.extractor_for(url).map(|e| e.name());
+
+        assert_eq!(name("https://fake.example/mix"), Ok("fake"));
+        assert_eq!(name("https://m.fake.example/mix"), Ok("fake"));
+        assert_eq!(name("https://soundcloud.com/artist/set"), Ok("yt-dlp"));
+        assert_eq!(name("file:///music/mix.mp3"), Ok("local"));
+        assert_eq!(name("mix.mp3"), Ok("local"));
+        assert!(name("ftp://example.com/mix.mp3").is_err());
+    }
+
+    #[test]
+    fn test_download_with_fake_extractor() {
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = fake_downloader(dir.path(), 1);
+
+        let track = downloader
+            .download("https://fake.example/mix?ref=share", Some(90), |_| {})
+            .unwrap();
+
+        assert_eq!(track.title, "Fake Mix");
+        assert_eq!(track.url, "https://fake.example/mix");
+        assert_eq!(track.file_path, "Fake Mix [mix1].mp3");
+        assert_eq!(track.bookmark(START_BOOKMARK).unwrap().position, 90);
+        assert!(
+            downloader
+                .config
+                .resolve_audio_path(&track.file_path)
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_save_stream_names_like_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = fake_downloader(dir.path(), 0);
+        let stream = dir.path().join("stream.mp3");
+        std::fs::write(&stream, b"audio").unwrap();
+
+        let track = Track::new(
+            "https://fake.example/mix".to_string(),
+            "Fake Mix".to_string(),
+            60,
+            String::new(),
+        );
+        let id = track.id;
+        let track = downloader.save_stream(track, &stream).unwrap();
+
+        assert_eq!(track.id, id);
+        assert_eq!(track.file_path, "Fake Mix [mix1].mp3");
+        assert_eq!(track.format.as_deref(), Some("mp3"));
+        assert!(!stream.exists());
+    }
 
     #[test]
     fn test_extract_video_id() {
@@ -226,5 +717,96 @@ mod tests {
             extract_video_id("https://youtube.com/watch?v=abc123&t=10"),
             Some("abc123".to_string())
         );
+
+        for url in [
+            "https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=RDAMVM",
+            "https://m.youtube.com/watch?feature=share&v=dQw4w9WgXcQ",
+            "https://www.youtube.com/shorts/dQw4w9WgXcQ?feature=share",
+            "https://www.youtube.com/live/dQw4w9WgXcQ",
+            "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?start=30",
+            "youtu.be/dQw4w9WgXcQ#t=10",
+        ] {
+            assert_eq!(
+                extract_video_id(url).as_deref(),
+                Some("dQw4w9WgXcQ"),
+                "{url}"
+            );
+        }
+
+        assert_eq!(
+            extract_video_id("https://www.youtube.com/@somechannel"),
+            None
+        );
+        assert_eq!(extract_video_id("https://notyoutube.com/watch?v=abc"), None);
+        assert_eq!(extract_video_id("file:///music/mix.mp3"), None);
+    }
+
+    #[test]
+    fn test_extract_start_time() {
+        assert_eq!(
+            extract_start_time("https://youtu.be/dQw4w9WgXcQ?t=90"),
+            Some(90)
+        );
+        assert_eq!(
+            extract_start_time("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1m30s"),
+            Some(90)
+        );
+        assert_eq!(
+            extract_start_time("youtu.be/dQw4w9WgXcQ#t=1h2s"),
+            Some(3602)
+        );
+        assert_eq!(
+            extract_start_time("https://www.youtube-nocookie.com/embed/abc?start=30"),
+            Some(30)
+        );
+        assert_eq!(
+            extract_start_time("https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            None
+        );
+        assert_eq!(extract_start_time("https://youtu.be/abc?t=soon"), None);
+        // Too long to be a time
+        assert_eq!(
+            extract_start_time("https://youtu.be/abc?t=9999999999999999h"),
+            None
+        );
+        assert_eq!(
+            extract_start_time("https://youtu.be/abc?t=18446744073709551615s1s"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_collection_url() {
+        assert!(is_collection_url(
+            "https://www.youtube.com/playlist?list=PL1234567890"
+        ));
+        assert!(is_collection_url("https://www.youtube.com/@somechannel"));
+        assert!(is_collection_url(
+            "https://www.youtube.com/channel/UC1234567890/videos"
+        ));
+        assert!(!is_collection_url(
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL1234567890"
+        ));
+        assert!(!is_collection_url("https://youtu.be/dQw4w9WgXcQ"));
+    }
+
+    #[test]
+    fn test_normalize_collection_url() {
+        assert_eq!(
+            normalize_collection_url("https://www.youtube.com/@somechannel"),
+            "https://www.youtube.com/@somechannel/videos"
+        );
+        assert_eq!(
+            normalize_collection_url("https://www.youtube.com/channel/UC123/"),
+            "https://www.youtube.com/channel/UC123/videos"
+        );
+        assert_eq!(
+            normalize_collection_url("https://www.youtube.com/@somechannel/streams"),
+            "https://www.youtube.com/@somechannel/streams"
+        );
+        assert_eq!(
+            normalize_collection_url("https://www.youtube.com/playlist?list=PL123"),
+            "https://www.youtube.com/playlist?list=PL123"
+        );
     }
 }
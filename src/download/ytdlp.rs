@@ -0,0 +1,424 @@
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use super::{
+    DownloadError, DownloadPhase, Extractor, MediaInfo, PlaylistEntry, PlaylistInfo,
+    normalize_collection_url,
+};
+use crate::config::{AudioConfig, Config};
+use crate::models::Chapter;
+
+#[derive(Debug, Deserialize)]
+struct YtDlpInfo {
+    id: String,
+    title: String,
+    duration: Option<f64>,
+    webpage_url: String,
+    chapters: Option<Vec<YtDlpChapter>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YtDlpChapter {
+    title: Option<String>,
+    start_time: f64,
+    end_time: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct YtDlpPlaylist {
+    title: Option<String>,
+    #[serde(default)]
+    entries: Vec<YtDlpPlaylistEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YtDlpPlaylistEntry {
+    id: String,
+    title: Option<String>,
+    url: Option<String>,
+    duration: Option<f64>,
+}
+
+/// Downloads from YouTube and the many other sites yt-dlp supports
+/// (SoundCloud, Bandcamp, Mixcloud, ...), extracting audio with ffmpeg.
+pub struct YtDlpExtractor {
+    program: String,
+    audio: AudioConfig,
+}
+
+impl YtDlpExtractor {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            program: config.download.yt_dlp.clone(),
+            audio: config.audio.clone(),
+        }
+    }
+
+    /// Audio extraction arguments for the configured format and quality.
+    fn audio_args(&self) -> Result<Vec<String>, DownloadError> {
+        // Without a target format yt-dlp keeps the best audio stream as is
+        let mut args = vec!["-x".to_string()];
+
+        if !self.audio.keeps_original() {
+            let quality = self
+                .audio
+                .yt_dlp_quality()
+                .map_err(|e| DownloadError::Unsupported(e.to_string()))?;
+            args.extend([
+                "--audio-format".to_string(),
+                self.audio.format.clone(),
+                "--audio-quality".to_string(),
+                quality,
+            ]);
+        }
+
+        Ok(args)
+    }
+
+    fn command(&self) -> Command {
+        Command::new(&self.program)
+    }
+
+    fn spawn_error(&self, e: io::Error) -> DownloadError {
+        if e.kind() == io::ErrorKind::NotFound {
+            DownloadError::missing(&self.program)
+        } else {
+            DownloadError::Unknown(format!("Failed to run {}: {e}", self.program))
+        }
+    }
+}
+
+impl Extractor for YtDlpExtractor {
+    fn name(&self) -> &'static str {
+        "yt-dlp"
+    }
+
+    fn check(&self) -> Result<(), DownloadError> {
+        if self.command().arg("--version").output().is_err() {
+            return Err(DownloadError::missing(&self.program));
+        }
+
+        // Needed by yt-dlp to extract audio
+        if Command::new("ffmpeg").arg("-version").output().is_err() {
+            return Err(DownloadError::missing("ffmpeg"));
+        }
+
+        Ok(())
+    }
+
+    fn info(&self, url: &str) -> Result<MediaInfo, DownloadError> {
+        let output = self
+            .command()
+            .args(["--dump-json", "--no-download", "--no-playlist", url])
+            .output()
+            .map_err(|e| self.spawn_error(e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(DownloadError::from_yt_dlp(output.status.code(), &stderr));
+        }
+
+        let info: YtDlpInfo = serde_json::from_slice(&output.stdout).map_err(parse_error)?;
+
+        Ok(MediaInfo {
+            id: Some(info.id),
+            title: info.title,
+            url: info.webpage_url,
+            duration: info.duration.unwrap_or(0.0) as u64,
+            chapters: info
+                .chapters
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(i, c)| Chapter {
+                    title: c.title.unwrap_or_else(|| format!("Chapter {}", i + 1)),
+                    start: c.start_time as u64,
+                    end: c.end_time as u64,
+                })
+                .collect(),
+        })
+    }
+
+    fn playlist(&self, url: &str) -> Result<PlaylistInfo, DownloadError> {
+        let url = normalize_collection_url(url);
+        let output = self
+            .command()
+            .args([
+                "--flat-playlist",
+                "--dump-single-json",
+                "--yes-playlist",
+                &url,
+            ])
+            .output()
+            .map_err(|e| self.spawn_error(e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(DownloadError::from_yt_dlp(output.status.code(), &stderr));
+        }
+
+        let playlist: YtDlpPlaylist =
+            serde_json::from_slice(&output.stdout).map_err(parse_error)?;
+
+        let entries = playlist
+            .entries
+            .into_iter()
+            .map(|entry| PlaylistEntry {
+                url: entry
+                    .url
+                    .filter(|u| u.starts_with("http"))
+                    .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", entry.id)),
+                title: entry.title.unwrap_or_else(|| entry.id.clone()),
+                duration: entry.duration.map(|d| d as u64),
+                id: entry.id,
+            })
+            .collect();
+
+        Ok(PlaylistInfo {
+            title: playlist
+                .title
+                .unwrap_or_else(|| "Untitled playlist".to_string()),
+            entries,
+        })
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        output: &Path,
+        cancel: &AtomicBool,
+        on_progress: &dyn Fn(DownloadPhase),
+    ) -> Result<PathBuf, DownloadError> {
+        let output_template = format!("{}.%(ext)s", output.display());
+
+        let mut child = self
+            .command()
+            .args(self.audio_args()?)
+            .args([
+                "--no-playlist",
+                "--progress",
+                "--newline",
+                "--progress-template",
+                "download:PROGRESS:%(progress._percent_str)s:%(progress._speed_str)s:%(progress._eta_str)s",
+                "--progress-template",
+                "postprocess:POSTPROCESS",
+                "-o",
+                &output_template,
+                "--continue", // Resume partial downloads from earlier attempts
+                "--part",
+                "--print",
+                "after_move:filepath",
+                url,
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.spawn_error(e))?;
+
+        let stderr = child.stderr.take().unwrap();
+        let reader = BufReader::new(stderr);
+        let mut stderr_output = String::new();
+
+        for line in reader.lines() {
+            if cancel.load(Ordering::SeqCst) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(DownloadError::Cancelled);
+            }
+
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+
+            if let Some(rest) = line.strip_prefix("PROGRESS:") {
+                let parts: Vec<&str> = rest.splitn(3, ':').collect();
+                if parts.len() == 3 {
+                    let percent = parts[0]
+                        .trim()
+                        .trim_end_matches('%')
+                        .parse::<f64>()
+                        .unwrap_or(0.0);
+                    let speed = parts[1].trim().to_string();
+                    let eta = parts[2].trim().to_string();
+                    on_progress(DownloadPhase::Downloading { percent, speed, eta });
+                }
+            } else if line.starts_with("POSTPROCESS") {
+                on_progress(DownloadPhase::Converting);
+            } else {
+                stderr_output.push_str(&line);
+                stderr_output.push('\n');
+            }
+        }
+
+        // stderr EOF — process has finished writing, read stdout and wait
+        let mut stdout = child.stdout.take().unwrap();
+        let mut stdout_str = String::new();
+        stdout
+            .read_to_string(&mut stdout_str)
+            .map_err(|e| DownloadError::Unknown(format!("Failed to read yt-dlp output: {e}")))?;
+
+        let status = child
+            .wait()
+            .map_err(|e| DownloadError::Unknown(format!("yt-dlp process failed: {e}")))?;
+
+        if !status.success() {
+            return Err(DownloadError::from_yt_dlp(status.code(), &stderr_output));
+        }
+
+        let file_path = PathBuf::from(stdout_str.trim());
+        if !file_path.as_os_str().is_empty() && file_path.exists() {
+            return Ok(file_path);
+        }
+
+        // Try to find the file
+        let expected_path = PathBuf::from(format!("{}.{}", output.display(), self.audio.format));
+        if !self.audio.keeps_original() && expected_path.exists() {
+            return Ok(expected_path);
+        }
+
+        Err(DownloadError::Unknown(
+            "Download completed but file not found".to_string(),
+        ))
+    }
+
+    fn stream(&self, url: &str) -> Result<Box<dyn Read + Send>, DownloadError> {
+        let mut child = self
+            .command()
+            .args([
+                "-f",
+                "bestaudio/best",
+                "--no-playlist",
+                "--quiet",
+                "--no-warnings",
+                "-o",
+                "-",
+                url,
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.spawn_error(e))?;
+
+        let stdout = child.stdout.take().unwrap();
+        // Drained alongside stdout so a chatty yt-dlp can't block on it
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr = thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output);
+            output
+        });
+        Ok(Box::new(ChildReader {
+            child,
+            stdout,
+            stderr: Some(stderr),
+        }))
+    }
+
+    fn check_availability(&self, url: &str) -> Result<(), DownloadError> {
+        let output = self
+            .command()
+            .args(["--simulate", "--no-playlist", url])
+            .output()
+            .map_err(|e| self.spawn_error(e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(DownloadError::from_yt_dlp(output.status.code(), &stderr));
+        }
+
+        Ok(())
+    }
+}
+
+/// A yt-dlp process's output, which kills the process when dropped. At the
+/// end of the output, a failed run is a read error carrying the
+/// `DownloadError`, so a cut-off stream isn't taken for a complete one.
+struct ChildReader {
+    child: Child,
+    stdout: ChildStdout,
+    /// Collects stderr, until the exit status has been checked
+    stderr: Option<JoinHandle<String>>,
+}
+
+impl Read for ChildReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n > 0 || buf.is_empty() {
+            return Ok(n);
+        }
+        let Some(stderr) = self.stderr.take() else {
+            return Ok(0);
+        };
+
+        let status = self.child.wait()?;
+        if !status.success() {
+            let stderr = stderr.join().unwrap_or_default();
+            return Err(io::Error::other(DownloadError::from_yt_dlp(
+                status.code(),
+                &stderr,
+            )));
+        }
+        Ok(0)
+    }
+}
+
+impl Drop for ChildReader {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn parse_error(e: serde_json::Error) -> DownloadError {
+    DownloadError::Unknown(format!("Failed to parse yt-dlp output: {e}"))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn fake_yt_dlp(dir: &Path, script: &str) -> YtDlpExtractor {
+        let program = dir.join("yt-dlp");
+        std::fs::write(&program, script).unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut config = Config::default();
+        config.download.yt_dlp = program.to_string_lossy().into_owned();
+        YtDlpExtractor::new(&config)
+    }
+
+    #[test]
+    fn test_stream_reports_failed_runs() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let complete = fake_yt_dlp(dir.path(), "#!/bin/sh\nprintf audio\n");
+        let mut audio = String::new();
+        complete
+            .stream("https://example.com/mix")
+            .unwrap()
+            .read_to_string(&mut audio)
+            .unwrap();
+        assert_eq!(audio, "audio");
+
+        // Cut off partway through
+        let cut_off = fake_yt_dlp(
+            dir.path(),
+            "#!/bin/sh\nprintf aud\necho 'ERROR: HTTP Error 403: Forbidden' >&2\nexit 1\n",
+        );
+        let mut audio = Vec::new();
+        let error = cut_off
+            .stream("https://example.com/mix")
+            .unwrap()
+            .read_to_end(&mut audio)
+            .unwrap_err();
+        assert_eq!(audio, b"aud");
+        let error = error.into_inner().unwrap().downcast::<DownloadError>();
+        assert!(error.unwrap().to_string().contains("403"));
+    }
+}
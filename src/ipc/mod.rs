@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
 
-use crate::models::{PlaybackState, RepeatMode, Track};
+use crate::models::{DownloadJob, LoopRegion, PlaybackState, RepeatMode, Track};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DaemonCommand {
@@ -15,6 +15,11 @@ pub enum DaemonCommand {
         tracks: Vec<Track>,
         start_index: usize,
     },
+    /// Play a track that isn't in the library while it downloads
+    PlayStream {
+        track: Track,
+        save: bool,
+    },
     Pause,
     Resume,
     Stop,
@@ -23,6 +28,16 @@ pub enum DaemonCommand {
     Seek {
         position: u64,
     },
+    NextChapter,
+    PreviousChapter,
+    /// Seek to the start of the current track's chapter at this index
+    Chapter {
+        index: usize,
+    },
+    /// Set or clear the A-B loop of the current track
+    SetLoop {
+        region: Option<LoopRegion>,
+    },
     SetVolume {
         volume: u8,
     },
@@ -36,6 +51,19 @@ pub enum DaemonCommand {
         track: Track,
     },
     QueueClear,
+    EnqueueDownload {
+        url: String,
+        alias: Option<String>,
+        playlist: Option<String>,
+    },
+    ListDownloads,
+    CancelDownload {
+        id: String,
+    },
+    RetryDownload {
+        id: String,
+    },
+    ClearDownloads,
     GetStatus,
     Shutdown,
 }
@@ -43,7 +71,8 @@ pub enum DaemonCommand {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DaemonResponse {
     Ok,
-    Status(PlaybackState),
+    Status(Box<PlaybackState>),
+    Downloads(Vec<DownloadJob>),
     Error(String),
 }
 
@@ -100,6 +129,10 @@ impl DaemonClient {
         self.send_command(DaemonCommand::Play { track })
     }
 
+    pub fn play_stream(&self, track: Track, save: bool) -> Result<DaemonResponse> {
+        self.send_command(DaemonCommand::PlayStream { track, save })
+    }
+
     #[allow(dead_code)]
     pub fn play_queue(&self, tracks: Vec<Track>, start_index: usize) -> Result<DaemonResponse> {
         self.send_command(DaemonCommand::PlayQueue {
@@ -134,6 +167,22 @@ impl DaemonClient {
         self.send_command(DaemonCommand::Seek { position })
     }
 
+    pub fn next_chapter(&self) -> Result<DaemonResponse> {
+        self.send_command(DaemonCommand::NextChapter)
+    }
+
+    pub fn previous_chapter(&self) -> Result<DaemonResponse> {
+        self.send_command(DaemonCommand::PreviousChapter)
+    }
+
+    pub fn chapter(&self, index: usize) -> Result<DaemonResponse> {
+        self.send_command(DaemonCommand::Chapter { index })
+    }
+
+    pub fn set_loop(&self, region: Option<LoopRegion>) -> Result<DaemonResponse> {
+        self.send_command(DaemonCommand::SetLoop { region })
+    }
+
     pub fn set_volume(&self, volume: u8) -> Result<DaemonResponse> {
         self.send_command(DaemonCommand::SetVolume { volume })
     }
@@ -158,9 +207,48 @@ impl DaemonClient {
         self.send_command(DaemonCommand::QueueClear)
     }
 
+    fn expect_downloads(&self, command: DaemonCommand) -> Result<Vec<DownloadJob>> {
+        match self.send_command(command)? {
+            DaemonResponse::Downloads(jobs) => Ok(jobs),
+            DaemonResponse::Error(e) => anyhow::bail!("{e}"),
+            _ => anyhow::bail!("Unexpected response"),
+        }
+    }
+
+    pub fn enqueue_download(
+        &self,
+        url: String,
+        alias: Option<String>,
+        playlist: Option<String>,
+    ) -> Result<DownloadJob> {
+        self.expect_downloads(DaemonCommand::EnqueueDownload {
+            url,
+            alias,
+            playlist,
+        })?
+        .pop()
+        .with_context(|| "Daemon did not return the queued download")
+    }
+
+    pub fn list_downloads(&self) -> Result<Vec<DownloadJob>> {
+        self.expect_downloads(DaemonCommand::ListDownloads)
+    }
+
+    pub fn cancel_download(&self, id: &str) -> Result<Vec<DownloadJob>> {
+        self.expect_downloads(DaemonCommand::CancelDownload { id: id.to_string() })
+    }
+
+    pub fn retry_download(&self, id: &str) -> Result<Vec<DownloadJob>> {
+        self.expect_downloads(DaemonCommand::RetryDownload { id: id.to_string() })
+    }
+
+    pub fn clear_downloads(&self) -> Result<Vec<DownloadJob>> {
+        self.expect_downloads(DaemonCommand::ClearDownloads)
+    }
+
     pub fn get_status(&self) -> Result<PlaybackState> {
         match self.send_command(DaemonCommand::GetStatus)? {
-            DaemonResponse::Status(state) => Ok(state),
+            DaemonResponse::Status(state) => Ok(*state),
             DaemonResponse::Error(e) => anyhow::bail!("{e}"),
             _ => anyhow::bail!("Unexpected response"),
         }
@@ -1,18 +1,24 @@
 mod audio;
 mod cli;
 mod config;
+mod convert;
 mod daemon;
 mod db;
 mod download;
 mod ipc;
 mod models;
+mod storage;
+mod subscription;
 mod tui;
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
 use tracing_subscriber::EnvFilter;
 
-use cli::{App, Cli, Commands, DaemonCommands};
+use cli::{
+    AliasCommands, App, Cli, Commands, DaemonCommands, DownloadCommands, LibraryCommands,
+    TrashCommands,
+};
 
 fn main() -> Result<()> {
     // Initialize logging
@@ -22,20 +28,101 @@ fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
-    let app = App::new()?;
+
+    // Runs before the app is set up, which fails on the problems it reports
+    if let Some(Commands::Doctor { json }) = cli.command {
+        return cli::run_doctor(json);
+    }
+
+    let mut app = App::new()?;
 
     // Default to TUI if no command given
     let command = cli.command.unwrap_or(Commands::Tui);
 
     match command {
-        Commands::Add { url, alias } => {
-            app.add(&url, alias.as_deref())?;
+        Commands::Add {
+            url,
+            alias,
+            save_playlist,
+            file,
+            jobs,
+            background,
+            format,
+            quality,
+            keep_original,
+        } => {
+            app.set_audio_format(format, quality, keep_original)?;
+            if url.as_deref() == Some("-") && (alias.is_some() || save_playlist) {
+                bail!(
+                    "--alias and --save-playlist can't be used with a batch; put aliases and playlists on its lines"
+                );
+            }
+            match (url.as_deref(), file.as_deref()) {
+                (Some("-"), _) if background => {
+                    app.add_batch_background("-")?;
+                }
+                (_, Some(file)) if background => {
+                    app.add_batch_background(file)?;
+                }
+                (Some("-"), _) => {
+                    app.add_batch("-", jobs)?;
+                }
+                (_, Some(file)) => {
+                    app.add_batch(file, jobs)?;
+                }
+                (Some(url), None) if background => {
+                    app.add_background(url, alias.as_deref())?;
+                }
+                (Some(url), None) => {
+                    app.add(url, alias.as_deref(), save_playlist)?;
+                }
+                (None, None) => unreachable!("clap requires a URL or --file"),
+            }
         }
         Commands::Remove { query } => {
             app.remove(&query)?;
         }
-        Commands::Play { query } => {
-            app.play(&query)?;
+        Commands::Edit {
+            query,
+            title,
+            alias,
+            artist,
+            notes,
+        } => {
+            app.edit(&query, title, alias, artist, notes)?;
+        }
+        Commands::Trim {
+            query,
+            start,
+            end,
+            clear,
+        } => {
+            app.trim(&query, start.as_deref(), end.as_deref(), clear)?;
+        }
+        Commands::Alias { command } => match command {
+            AliasCommands::Add { query, aliases } => {
+                app.alias_add(&query, &aliases)?;
+            }
+            AliasCommands::Rm { aliases } => {
+                app.alias_remove(&aliases)?;
+            }
+            AliasCommands::List { query } => {
+                app.alias_list(query.as_deref())?;
+            }
+        },
+        Commands::Trash { command } => match command {
+            TrashCommands::List => {
+                app.trash_list()?;
+            }
+            TrashCommands::Restore { query } => {
+                app.trash_restore(query.as_deref())?;
+            }
+            TrashCommands::Empty { yes } => {
+                app.trash_empty(yes)?;
+            }
+        },
+        Commands::Play { query, save } => {
+            app.play(&query, save)?;
         }
         Commands::Pause => {
             app.pause()?;
@@ -49,6 +136,30 @@ fn main() -> Result<()> {
         Commands::Seek { position } => {
             app.seek(&position)?;
         }
+        Commands::NextChapter => {
+            app.next_chapter()?;
+        }
+        Commands::PrevChapter => {
+            app.previous_chapter()?;
+        }
+        Commands::Chapter { chapter } => {
+            app.chapter(&chapter)?;
+        }
+        Commands::Chapters { query, refresh } => {
+            app.chapters(query.as_deref(), refresh)?;
+        }
+        Commands::Mark { name, at, delete } => {
+            app.mark(&name, &at, delete)?;
+        }
+        Commands::Marks { query } => {
+            app.marks(query.as_deref())?;
+        }
+        Commands::Goto { name } => {
+            app.goto(&name)?;
+        }
+        Commands::Loop { a, b } => {
+            app.ab_loop(a.as_deref(), b.as_deref())?;
+        }
         Commands::Volume { level } => {
             app.volume(level)?;
         }
@@ -81,9 +192,70 @@ fn main() -> Result<()> {
         Commands::Import { file } => {
             app.import(&file)?;
         }
-        Commands::Check => {
-            app.check()?;
+        Commands::Check {
+            stale,
+            offline,
+            jobs,
+        } => {
+            app.check(stale.as_deref(), offline, jobs)?;
+        }
+        Commands::Subscribe { url, into, filter } => {
+            app.subscribe(&url, into.as_deref(), filter.as_deref())?;
         }
+        Commands::Unsubscribe { query } => {
+            app.unsubscribe(&query)?;
+        }
+        Commands::Subscriptions => {
+            app.subscriptions()?;
+        }
+        Commands::Sync => {
+            app.sync()?;
+        }
+        Commands::Downloads { command } => match command.unwrap_or(DownloadCommands::List) {
+            DownloadCommands::List => {
+                app.downloads()?;
+            }
+            DownloadCommands::Cancel { id } => {
+                app.download_cancel(&id)?;
+            }
+            DownloadCommands::Retry { id } => {
+                app.download_retry(&id)?;
+            }
+            DownloadCommands::Clear => {
+                app.download_clear()?;
+            }
+        },
+        Commands::Convert {
+            query,
+            all: _,
+            format,
+            bitrate,
+            jobs,
+            force,
+        } => {
+            app.convert(
+                query.as_deref(),
+                format.as_deref(),
+                bitrate.as_deref(),
+                jobs,
+                force,
+            )?;
+        }
+        Commands::Du { limit } => {
+            app.du(limit)?;
+        }
+        Commands::Gc { dry_run, yes } => {
+            app.gc(dry_run, yes)?;
+        }
+        Commands::Dedupe { dry_run, yes } => {
+            app.dedupe(dry_run, yes)?;
+        }
+        Commands::Doctor { .. } => unreachable!("handled before the app is set up"),
+        Commands::Library { command } => match command {
+            LibraryCommands::Move { path } => {
+                app.library_move(&path)?;
+            }
+        },
         Commands::Tui => {
             // Ensure daemon is running for playback
             let client = ipc::DaemonClient::new(app.config.socket_path());
@@ -1,42 +1,248 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use uuid::Uuid;
 
+use crate::download::{DownloadError, extract_video_id};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
     pub id: Uuid,
     pub url: String,
+    /// YouTube video ID, which identifies the video whatever form its URL takes
+    #[serde(default)]
+    pub video_id: Option<String>,
+    /// Title as fetched from the source
     pub title: String,
     pub alias: Option<String>,
+    /// Title set with `mixyt edit`, shown instead of the fetched one
+    #[serde(default)]
+    pub custom_title: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
     pub duration: u64,
     pub added_at: DateTime<Utc>,
     pub file_path: String,
-    pub available: bool,
+    /// Whether the audio file was on disk when last looked at
+    #[serde(default)]
+    pub file_status: FileStatus,
+    #[serde(default)]
+    pub file_checked_at: Option<DateTime<Utc>>,
+    /// Whether the source video could still be downloaded when last checked
+    #[serde(default)]
+    pub source_status: SourceStatus,
+    #[serde(default)]
+    pub source_checked_at: Option<DateTime<Utc>>,
+    /// Audio format of the stored file, e.g. "mp3" or "opus"
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Bitrate of the stored file in kbps, if known
+    #[serde(default)]
+    pub bitrate: Option<u32>,
+    /// When the daemon last started playing this track
+    #[serde(default)]
+    pub last_played_at: Option<DateTime<Utc>>,
+    /// The audio file was evicted to stay under `storage.max_size` and is
+    /// downloaded again when the track is played
+    #[serde(default)]
+    pub remote_only: bool,
+    /// Chapters of a long mix, as listed by the source
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    /// Where playback starts and ends, in seconds, to skip an intro or
+    /// outro. The file itself is untouched
+    #[serde(default)]
+    pub trim_start: Option<u64>,
+    #[serde(default)]
+    pub trim_end: Option<u64>,
+    /// Named positions set with `mixyt mark`, in order of position
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
 }
 
 impl Track {
     pub fn new(url: String, title: String, duration: u64, file_path: String) -> Self {
         Self {
             id: Uuid::new_v4(),
+            video_id: extract_video_id(&url),
             url,
             title,
             alias: None,
+            custom_title: None,
+            artist: None,
+            notes: None,
             duration,
             added_at: Utc::now(),
             file_path,
-            available: true,
+            file_status: FileStatus::Present,
+            file_checked_at: None,
+            // It was just fetched from the source
+            source_status: SourceStatus::Available,
+            source_checked_at: None,
+            format: None,
+            bitrate: None,
+            last_played_at: None,
+            remote_only: false,
+            chapters: Vec::new(),
+            trim_start: None,
+            trim_end: None,
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// Whether playing the track can work: it has its audio file, or the
+    /// file was evicted and can be downloaded again.
+    pub fn is_playable(&self) -> bool {
+        if self.remote_only {
+            self.source_status != SourceStatus::Unavailable
+        } else {
+            self.file_status == FileStatus::Present
         }
     }
 
     pub fn display_name(&self) -> &str {
-        self.alias.as_deref().unwrap_or(&self.title)
+        self.alias.as_deref().unwrap_or(self.display_title())
+    }
+
+    /// The edited title if there is one, otherwise the fetched one.
+    pub fn display_title(&self) -> &str {
+        self.custom_title.as_deref().unwrap_or(&self.title)
+    }
+
+    /// Position playback starts at.
+    pub fn play_start(&self) -> u64 {
+        self.trim_start.unwrap_or(0)
+    }
+
+    /// Position playback ends at.
+    pub fn play_end(&self) -> u64 {
+        match self.trim_end {
+            Some(end) if self.duration == 0 => end,
+            Some(end) => end.min(self.duration),
+            None => self.duration,
+        }
+    }
+
+    /// The length that plays, after trimming.
+    pub fn play_duration(&self) -> u64 {
+        self.play_end().saturating_sub(self.play_start())
     }
 
     pub fn format_duration(&self) -> String {
-        let minutes = self.duration / 60;
-        let seconds = self.duration % 60;
+        let duration = self.play_duration();
+        let minutes = duration / 60;
+        let seconds = duration % 60;
         format!("{minutes}:{seconds:02}")
     }
+
+    /// Add a bookmark, replacing any with the same name.
+    pub fn set_bookmark(&mut self, name: &str, position: u64) {
+        self.bookmarks.retain(|b| b.name != name);
+        let index = self.bookmarks.partition_point(|b| b.position <= position);
+        self.bookmarks.insert(
+            index,
+            Bookmark {
+                name: name.to_string(),
+                position,
+            },
+        );
+    }
+
+    /// The bookmark with this name, ignoring case if none matches exactly.
+    pub fn bookmark(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|b| b.name == name).or_else(|| {
+            self.bookmarks
+                .iter()
+                .find(|b| b.name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Index of the chapter playing at `position` seconds, if any.
+    pub fn chapter_at(&self, position: u64) -> Option<usize> {
+        self.chapters.iter().rposition(|c| c.start <= position)
+    }
+}
+
+/// A named position in a track, in seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub position: u64,
+}
+
+/// A named section of a track, with its start and end in seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    #[default]
+    Present,
+    Missing,
+}
+
+impl std::fmt::Display for FileStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            FileStatus::Present => "present",
+            FileStatus::Missing => "missing",
+        };
+        f.pad(s)
+    }
+}
+
+impl std::str::FromStr for FileStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "present" => Ok(FileStatus::Present),
+            "missing" => Ok(FileStatus::Missing),
+            _ => Err(format!("Invalid file status: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceStatus {
+    /// Never checked, or the last check couldn't tell
+    #[default]
+    Unknown,
+    Available,
+    /// Removed, private or otherwise no longer downloadable
+    Unavailable,
+}
+
+impl std::fmt::Display for SourceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SourceStatus::Unknown => "unknown",
+            SourceStatus::Available => "available",
+            SourceStatus::Unavailable => "unavailable",
+        };
+        f.pad(s)
+    }
+}
+
+impl std::str::FromStr for SourceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unknown" => Ok(SourceStatus::Unknown),
+            "available" => Ok(SourceStatus::Available),
+            "unavailable" => Ok(SourceStatus::Unavailable),
+            _ => Err(format!("Invalid source status: {s}")),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,7 +253,6 @@ pub struct Playlist {
 }
 
 impl Playlist {
-    #[allow(dead_code)]
     pub fn new(name: String) -> Self {
         Self {
             id: Uuid::new_v4(),
@@ -64,6 +269,138 @@ pub struct PlaylistTrack {
     pub position: i32,
 }
 
+/// A removed track, kept until the trash is emptied so it can be restored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedTrack {
+    pub track: Track,
+    /// The playlists the track was in, at its positions there
+    pub playlists: Vec<PlaylistTrack>,
+    /// All its aliases, including the one it was shown by
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Name of its audio file in the trash directory, if it had one of its own
+    pub file: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub url: String,
+    pub title: String,
+    pub playlist_id: Option<Uuid>,
+    pub filter: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+}
+
+impl Subscription {
+    pub fn new(
+        url: String,
+        title: String,
+        playlist_id: Option<Uuid>,
+        filter: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            url,
+            title,
+            playlist_id,
+            filter,
+            created_at: Utc::now(),
+            last_synced_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        };
+        f.pad(s)
+    }
+}
+
+impl std::str::FromStr for JobStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "done" => Ok(JobStatus::Done),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            _ => Err(format!("Invalid job status: {s}")),
+        }
+    }
+}
+
+/// A download owned by the daemon's background queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadJob {
+    pub id: Uuid,
+    pub url: String,
+    pub alias: Option<String>,
+    pub playlist: Option<String>,
+    pub status: JobStatus,
+    pub title: Option<String>,
+    pub error: Option<DownloadError>,
+    pub track_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Live progress of a running job (not persisted)
+    #[serde(default)]
+    pub progress: Option<String>,
+}
+
+impl DownloadJob {
+    pub fn new(url: String, alias: Option<String>, playlist: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            url,
+            alias,
+            playlist,
+            status: JobStatus::Queued,
+            title: None,
+            error: None,
+            track_id: None,
+            created_at: Utc::now(),
+            progress: None,
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.url)
+    }
+
+    pub fn short_id(&self) -> String {
+        self.id.to_string()[..8].to_string()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum RepeatMode {
     #[default]
@@ -102,9 +439,26 @@ pub struct PlaybackState {
     pub queue_index: usize,
     pub is_playing: bool,
     pub volume: u8,
+    /// Position in whole seconds, for display
     pub position: u64,
+    /// Position as the player reports it, for A-B loops
+    #[serde(default)]
+    pub precise_position: Duration,
     pub shuffle: bool,
     pub repeat: RepeatMode,
+    /// Index of the current track's chapter at `position`
+    #[serde(default)]
+    pub chapter: Option<usize>,
+    /// Section of the current track played over and over
+    #[serde(default)]
+    pub loop_region: Option<LoopRegion>,
+}
+
+/// An A-B loop: playback jumps back to `start` once it passes `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    pub start: Duration,
+    pub end: Duration,
 }
 
 impl PlaybackState {
@@ -114,6 +468,32 @@ impl PlaybackState {
             ..Default::default()
         }
     }
+
+    /// Set the position, updating the chapter to match.
+    pub fn set_position(&mut self, position: u64) {
+        self.set_precise_position(Duration::from_secs(position));
+    }
+
+    pub fn set_precise_position(&mut self, position: Duration) {
+        self.position = position.as_secs();
+        self.precise_position = position;
+        self.chapter = self
+            .current_track
+            .as_ref()
+            .and_then(|t| t.chapter_at(self.position));
+    }
+
+    pub fn current_chapter(&self) -> Option<&Chapter> {
+        let track = self.current_track.as_ref()?;
+        track.chapters.get(self.chapter?)
+    }
+}
+
+/// One of a track's aliases. Each alias belongs to one track only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackAlias {
+    pub alias: String,
+    pub track_id: Uuid,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -123,6 +503,8 @@ pub struct LibraryExport {
     pub tracks: Vec<Track>,
     pub playlists: Vec<Playlist>,
     pub playlist_tracks: Vec<PlaylistTrack>,
+    #[serde(default)]
+    pub aliases: Vec<TrackAlias>,
 }
 
 impl LibraryExport {
@@ -130,6 +512,7 @@ impl LibraryExport {
         tracks: Vec<Track>,
         playlists: Vec<Playlist>,
         playlist_tracks: Vec<PlaylistTrack>,
+        aliases: Vec<TrackAlias>,
     ) -> Self {
         Self {
             version: "1.0".to_string(),
@@ -137,6 +520,46 @@ impl LibraryExport {
             tracks,
             playlists,
             playlist_tracks,
+            aliases,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_position_updates_chapter() {
+        let mut track = Track::new("mix".to_string(), "Mix".to_string(), 600, String::new());
+        track.chapters = [("Intro", 0, 100), ("Middle", 100, 300)]
+            .into_iter()
+            .map(|(title, start, end)| Chapter {
+                title: title.to_string(),
+                start,
+                end,
+            })
+            .collect();
+
+        let mut state = PlaybackState::new();
+        state.set_position(50);
+        assert_eq!(state.chapter, None);
+
+        state.current_track = Some(track);
+        state.set_precise_position(Duration::from_millis(99_900));
+        assert_eq!(state.position, 99);
+        assert_eq!(state.chapter, Some(0));
+        state.set_position(100);
+        assert_eq!(state.chapter, Some(1));
+        assert_eq!(state.current_chapter().unwrap().title, "Middle");
+        // Seeking back moves it back
+        state.set_position(0);
+        assert_eq!(state.chapter, Some(0));
+
+        // Chapters that don't start at 0 leave the beginning outside any
+        state.current_track.as_mut().unwrap().chapters.remove(0);
+        state.set_position(10);
+        assert_eq!(state.chapter, None);
+        assert!(state.current_chapter().is_none());
+    }
+}
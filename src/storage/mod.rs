@@ -0,0 +1,673 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::download::{
+    DownloadPhase, Downloader, audio_file_key, audio_file_prefix, extract_video_id, probe_bitrate,
+};
+use crate::models::{DownloadJob, PlaylistTrack, Track, TrashedTrack};
+
+/// Files modified more recently than this may still be being written, by
+/// a download in this or another process, so `find_garbage` skips them.
+const RECENT_FILE_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Size of a track's audio file, or `None` if it has no file on disk.
+pub fn track_size(config: &Config, track: &Track) -> Option<u64> {
+    if track.remote_only {
+        return None;
+    }
+    fs::metadata(config.resolve_audio_path(&track.file_path))
+        .ok()
+        .map(|m| m.len())
+}
+
+/// Total size of the tracks' audio files. Shared files count once.
+pub fn library_size(config: &Config, tracks: &[Track]) -> u64 {
+    let mut seen = HashSet::new();
+    tracks
+        .iter()
+        .filter(|t| seen.insert(t.file_path.as_str()))
+        .filter_map(|t| track_size(config, t))
+        .sum()
+}
+
+/// Evict least-recently-played tracks until the library fits in
+/// `storage.max_size`, returning the evicted tracks. Their metadata is kept
+/// and the audio is downloaded again when they are next played.
+///
+/// Tracks in `keep` (e.g. ones that were just downloaded) are never evicted.
+pub fn enforce_quota(config: &Config, db: &Database, keep: &[Uuid]) -> Result<Vec<Track>> {
+    let Some(max_size) = config.storage.max_size_bytes()? else {
+        return Ok(Vec::new());
+    };
+
+    let tracks = db.get_all_tracks()?;
+    let mut used = library_size(config, &tracks);
+    if used <= max_size {
+        return Ok(Vec::new());
+    }
+
+    // Deleting a shared file would take it away from the other tracks too
+    let mut users: HashMap<&str, usize> = HashMap::new();
+    for track in tracks.iter().filter(|t| !t.remote_only) {
+        *users.entry(track.file_path.as_str()).or_default() += 1;
+    }
+
+    let mut candidates: Vec<_> = tracks
+        .iter()
+        .filter(|t| !keep.contains(&t.id) && users.get(t.file_path.as_str()) == Some(&1))
+        .filter_map(|t| track_size(config, t).map(|size| (t, size)))
+        .collect();
+    // A track that was never played counts as used when it was added
+    candidates.sort_by_key(|(t, _)| t.last_played_at.unwrap_or(t.added_at));
+
+    let mut evicted = Vec::new();
+    for (track, size) in candidates {
+        if used <= max_size {
+            break;
+        }
+
+        remove_file(&config.resolve_audio_path(&track.file_path))?;
+        db.update_track_remote_only(&track.id, true)?;
+
+        used = used.saturating_sub(size);
+        evicted.push(track.clone());
+    }
+
+    Ok(evicted)
+}
+
+/// Download an evicted track's audio again and point the track at it.
+pub fn restore_track(
+    downloader: &Downloader,
+    db: &Database,
+    track: &Track,
+    on_progress: impl Fn(DownloadPhase),
+) -> Result<Track> {
+    let fetched = downloader.download(&track.url, None, on_progress)?;
+    db.update_track_audio(
+        &track.id,
+        &fetched.file_path,
+        fetched.format.as_deref(),
+        fetched.bitrate,
+    )?;
+
+    Ok(Track {
+        file_path: fetched.file_path,
+        format: fetched.format,
+        bitrate: fetched.bitrate,
+        remote_only: false,
+        ..track.clone()
+    })
+}
+
+/// Leftovers found by `mixyt gc`.
+#[derive(Debug, Default)]
+pub struct Garbage {
+    /// Partial downloads, conversions and streams, with their sizes
+    pub temp_files: Vec<(PathBuf, u64)>,
+    /// Audio files no track points at, with their sizes
+    pub orphan_files: Vec<(PathBuf, u64)>,
+    /// Tracks whose audio file is gone, with an orphan file that looks like
+    /// theirs if there is one
+    pub missing: Vec<(Track, Option<PathBuf>)>,
+    /// Playlist entries whose track or playlist no longer exists
+    pub dangling_entries: Vec<PlaylistTrack>,
+}
+
+impl Garbage {
+    pub fn is_empty(&self) -> bool {
+        self.temp_files.is_empty()
+            && self.orphan_files.is_empty()
+            && self.missing.is_empty()
+            && self.dangling_entries.is_empty()
+    }
+
+    /// Bytes freed by deleting the temp and orphan files.
+    pub fn size(&self) -> u64 {
+        self.temp_files
+            .iter()
+            .chain(&self.orphan_files)
+            .map(|(_, size)| size)
+            .sum()
+    }
+}
+
+/// Partial files left behind by yt-dlp, ffmpeg or `mixyt convert`.
+fn is_temp_file(name: &str) -> bool {
+    name.ends_with(".part")
+        || name.ends_with(".ytdl")
+        || name.contains(".part-Frag")
+        || name.contains(".temp.")
+        || (name.starts_with('.') && name.contains(".converting."))
+}
+
+/// Find files and rows that no longer belong to anything.
+///
+/// Files that may still be written are left out: those of `active_jobs`,
+/// recently modified ones, and the stream directory unless
+/// `include_streams` is set, since the daemon may be playing from it.
+pub fn find_garbage(
+    config: &Config,
+    db: &Database,
+    include_streams: bool,
+    active_jobs: &[DownloadJob],
+) -> Result<Garbage> {
+    let tracks = db.get_all_tracks()?;
+    let used: HashSet<PathBuf> = tracks
+        .iter()
+        .map(|t| config.resolve_audio_path(&t.file_path))
+        .collect();
+    let present_stems: HashSet<_> = used
+        .iter()
+        .filter(|p| p.exists())
+        .filter_map(|p| p.file_stem())
+        .collect();
+    let job_prefixes: Vec<String> = active_jobs
+        .iter()
+        .filter_map(|job| job.title.as_deref())
+        .map(audio_file_prefix)
+        .collect();
+    let is_recent = |metadata: &fs::Metadata| {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < RECENT_FILE_AGE)
+    };
+
+    let mut garbage = Garbage::default();
+    let mut unclaimed = Vec::new();
+
+    for entry in fs::read_dir(config.audio_dir())? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let downloading = job_prefixes.iter().any(|prefix| name.starts_with(prefix));
+        if !metadata.is_file() || used.contains(&path) || downloading || is_recent(&metadata) {
+            continue;
+        }
+
+        // An unused file next to a track's file with the same name is the
+        // source yt-dlp didn't get to delete after converting. If the
+        // track's own file is gone it's kept, to be re-linked below
+        let leftover = path.file_stem().is_some_and(|s| present_stems.contains(s));
+        if is_temp_file(&name) || leftover {
+            garbage.temp_files.push((path, metadata.len()));
+        } else {
+            unclaimed.push((path, metadata.len()));
+        }
+    }
+
+    if include_streams && config.stream_dir().exists() {
+        for entry in fs::read_dir(config.stream_dir())? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !is_recent(&metadata) {
+                garbage.temp_files.push((entry.path(), metadata.len()));
+            }
+        }
+    }
+
+    for track in tracks.iter().filter(|t| !t.remote_only) {
+        if config.resolve_audio_path(&track.file_path).exists() {
+            continue;
+        }
+
+        // A file with the track's own name but another extension is the
+        // best match. Files are named "<title> [<key>]", so a renamed title
+        // still matches by key
+        let stem = Path::new(&track.file_path).file_stem();
+        let video_id = extract_video_id(&track.url);
+        let suffix = format!("[{}]", audio_file_key(video_id.as_deref(), track));
+        let relink = unclaimed
+            .iter()
+            .position(|(path, _)| stem.is_some() && path.file_stem() == stem)
+            .or_else(|| {
+                unclaimed.iter().position(|(path, _)| {
+                    path.file_stem()
+                        .is_some_and(|s| s.to_string_lossy().ends_with(&suffix))
+                })
+            })
+            .map(|i| unclaimed.remove(i).0);
+        garbage.missing.push((track.clone(), relink));
+    }
+    garbage.orphan_files = unclaimed;
+
+    let track_ids: HashSet<Uuid> = tracks.iter().map(|t| t.id).collect();
+    let playlist_ids: HashSet<Uuid> = db.get_all_playlists()?.iter().map(|p| p.id).collect();
+    garbage.dangling_entries = db
+        .get_all_playlist_tracks()?
+        .into_iter()
+        .filter(|e| !track_ids.contains(&e.track_id) || !playlist_ids.contains(&e.playlist_id))
+        .collect();
+
+    garbage.temp_files.sort();
+    garbage.orphan_files.sort();
+    Ok(garbage)
+}
+
+/// Delete the files `find_garbage` found, re-link tracks to their files where
+/// possible and mark the rest remote only, so they are downloaded again.
+pub fn collect_garbage(config: &Config, db: &Database, garbage: &Garbage) -> Result<()> {
+    for (path, _) in garbage.temp_files.iter().chain(&garbage.orphan_files) {
+        remove_file(path)?;
+    }
+
+    for (track, relink) in &garbage.missing {
+        match relink {
+            Some(path) => db.update_track_audio(
+                &track.id,
+                &config.relative_audio_path(path),
+                path.extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .as_deref(),
+                probe_bitrate(path),
+            )?,
+            None => db.update_track_remote_only(&track.id, true)?,
+        }
+    }
+
+    for entry in &garbage.dangling_entries {
+        db.remove_track_from_playlist(&entry.playlist_id, &entry.track_id)?;
+    }
+
+    Ok(())
+}
+
+/// Tracks of the same video, found by `mixyt dedupe`.
+#[derive(Debug)]
+pub struct Duplicates {
+    /// The track the others are merged into
+    pub keep: Track,
+    pub duplicates: Vec<Track>,
+}
+
+/// Group tracks by video ID. The track kept is one with its audio on disk,
+/// and the oldest of those.
+pub fn find_duplicates(config: &Config, db: &Database) -> Result<Vec<Duplicates>> {
+    let mut videos: HashMap<String, Vec<Track>> = HashMap::new();
+    for track in db.get_all_tracks()? {
+        // Duplicates weren't given the video ID when it was backfilled
+        if let Some(id) = track
+            .video_id
+            .clone()
+            .or_else(|| extract_video_id(&track.url))
+        {
+            videos.entry(id).or_default().push(track);
+        }
+    }
+
+    let mut found: Vec<Duplicates> = videos
+        .into_values()
+        .filter(|tracks| tracks.len() > 1)
+        .map(|mut tracks| {
+            tracks.sort_by_key(|t| (track_size(config, t).is_none(), t.added_at));
+            let keep = tracks.remove(0);
+            Duplicates {
+                keep,
+                duplicates: tracks,
+            }
+        })
+        .collect();
+    found.sort_by_key(|d| d.keep.added_at);
+
+    Ok(found)
+}
+
+/// Merge duplicates into the kept tracks and delete their audio files.
+pub fn merge_duplicates(config: &Config, db: &Database, found: &[Duplicates]) -> Result<()> {
+    for group in found {
+        for duplicate in &group.duplicates {
+            db.merge_track(&group.keep.id, &duplicate.id)?;
+        }
+    }
+
+    // A file is only deleted once no track is left using it
+    let used: HashSet<String> = db
+        .get_all_tracks()?
+        .into_iter()
+        .map(|t| t.file_path)
+        .collect();
+    for duplicate in found.iter().flat_map(|g| &g.duplicates) {
+        if !duplicate.remote_only && !used.contains(&duplicate.file_path) {
+            remove_file(&config.resolve_audio_path(&duplicate.file_path))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Move a track to the trash: its audio file goes to the trash directory
+/// (unless another track shares it) and it is deleted from the library,
+/// remembering its playlists.
+pub fn trash_track(config: &Config, db: &Database, track: &Track) -> Result<TrashedTrack> {
+    let path = config.resolve_audio_path(&track.file_path);
+    let shared = db
+        .get_all_tracks()?
+        .iter()
+        .any(|t| t.id != track.id && t.file_path == track.file_path);
+
+    let file = if !track.remote_only && !shared && path.exists() {
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        let name = format!("{}.{extension}", track.id);
+        fs::create_dir_all(config.trash_dir())?;
+        fs::rename(&path, config.trash_dir().join(&name))?;
+        Some(name)
+    } else {
+        None
+    };
+
+    let trashed = TrashedTrack {
+        track: track.clone(),
+        playlists: db.get_track_playlist_entries(&track.id)?,
+        aliases: db.get_track_aliases(&track.id)?,
+        file,
+        deleted_at: Utc::now(),
+    };
+    if let Err(e) = db.trash_track(&trashed) {
+        // Put the file back so the library stays as it was
+        if let Some(ref name) = trashed.file {
+            let _ = fs::rename(config.trash_dir().join(name), &path);
+        }
+        return Err(e);
+    }
+
+    Ok(trashed)
+}
+
+/// Bring a trashed track back, with its audio file and playlists.
+pub fn restore_trashed(config: &Config, db: &Database, trashed: &TrashedTrack) -> Result<()> {
+    let track = &trashed.track;
+    if let Some(existing) = db.get_track_by_source(&track.url)? {
+        bail!(
+            "'{}' is in the library again as '{}'",
+            track.display_name(),
+            existing.display_name()
+        );
+    }
+
+    let path = config.resolve_audio_path(&track.file_path);
+    if let Some(ref name) = trashed.file {
+        if path.exists() {
+            bail!("{} is in the way of the restored file", path.display());
+        }
+        fs::rename(config.trash_dir().join(name), &path)?;
+    }
+
+    if let Err(e) = db.restore_trashed(trashed) {
+        if let Some(ref name) = trashed.file {
+            let _ = fs::rename(&path, config.trash_dir().join(name));
+        }
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Permanently delete trashed tracks removed before `before`, or all of
+/// them, returning what was deleted.
+pub fn empty_trash(
+    config: &Config,
+    db: &Database,
+    before: Option<DateTime<Utc>>,
+) -> Result<Vec<TrashedTrack>> {
+    let expired: Vec<TrashedTrack> = db
+        .get_trash()?
+        .into_iter()
+        .filter(|t| before.is_none_or(|before| t.deleted_at < before))
+        .collect();
+
+    for trashed in &expired {
+        if let Some(ref name) = trashed.file {
+            remove_file(&config.trash_dir().join(name))?;
+        }
+        db.delete_trashed(&trashed.track.id)?;
+    }
+
+    Ok(expired)
+}
+
+/// Empty tracks that have been in the trash longer than `storage.trash_days`.
+pub fn expire_trash(config: &Config, db: &Database) -> Result<Vec<TrashedTrack>> {
+    match config.storage.trash_days {
+        0 => Ok(Vec::new()),
+        days => empty_trash(config, db, Some(Utc::now() - Duration::days(days as i64))),
+    }
+}
+
+/// Size of the audio files in the trash.
+pub fn trash_size(config: &Config, trash: &[TrashedTrack]) -> u64 {
+    trash
+        .iter()
+        .filter_map(|t| t.file.as_ref())
+        .filter_map(|name| fs::metadata(config.trash_dir().join(name)).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Remove a file, treating one that is already gone as removed.
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config for a library in a new temporary directory, and an empty
+    /// database.
+    fn test_library() -> (tempfile::TempDir, Config, Database) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.path = dir.path().to_path_buf();
+        config.ensure_dirs().unwrap();
+        (dir, config, Database::open_in_memory().unwrap())
+    }
+
+    #[test]
+    fn test_enforce_quota() {
+        let (_dir, mut config, db) = test_library();
+        config.storage.max_size = Some("25".to_string());
+
+        let now = Utc::now();
+        let mut tracks = Vec::new();
+        for (name, played) in [("old", Some(3)), ("new", Some(1)), ("unplayed", None)] {
+            let mut track = Track::new(
+                format!("https://example.com/{name}"),
+                name.to_string(),
+                60,
+                format!("{name}.mp3"),
+            );
+            track.added_at = now - Duration::days(2);
+            track.last_played_at = played.map(|days| now - Duration::days(days));
+            fs::write(config.resolve_audio_path(&track.file_path), [0u8; 10]).unwrap();
+            db.insert_track(&track).unwrap();
+            tracks.push(track);
+        }
+
+        // 30 bytes against a 25 byte quota: the least recently used track goes
+        let evicted = enforce_quota(&config, &db, &[]).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].title, "old");
+        assert!(!config.resolve_audio_path("old.mp3").exists());
+        assert!(db.get_track(&tracks[0].id).unwrap().unwrap().remote_only);
+
+        // Kept tracks stay even when that leaves the library over quota
+        config.storage.max_size = Some("0".to_string());
+        let evicted = enforce_quota(&config, &db, &[tracks[2].id]).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].title, "new");
+        assert!(config.resolve_audio_path("unplayed.mp3").exists());
+    }
+
+    #[test]
+    fn test_find_garbage() {
+        let (_dir, config, db) = test_library();
+
+        let track = |id: &str, file: &str| {
+            let track = Track::new(
+                format!("https://www.youtube.com/watch?v={id}"),
+                format!("Mix {id}"),
+                60,
+                file.to_string(),
+            );
+            db.insert_track(&track).unwrap();
+            track
+        };
+        track("aaa", "Mix aaa [aaa].mp3");
+        let moved = track("bbb", "Mix bbb [bbb].mp3");
+        let converted = track("ccc", "Mix ccc [ccc].mp3");
+
+        let old = std::time::SystemTime::now() - RECENT_FILE_AGE * 2;
+        for file in [
+            "Mix aaa [aaa].mp3",
+            "Mix aaa [aaa].webm",
+            "Mix ccc [ccc].webm",
+            "Mix ddd [ddd].mp3.part",
+            "Mix eee [eee].mp3.part",
+            "Renamed [bbb].opus",
+            "Unknown.mp3",
+        ] {
+            let path = config.audio_dir().join(file);
+            fs::write(&path, b"audio").unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(old).unwrap();
+        }
+        // Being written by another process
+        fs::write(config.audio_dir().join("Mix fff [fff].webm.part"), b"audio").unwrap();
+
+        let mut job = DownloadJob::new("https://youtu.be/eee".to_string(), None, None);
+        job.title = Some("Mix eee".to_string());
+        let jobs = [job];
+        let garbage = find_garbage(&config, &db, false, &jobs).unwrap();
+        let names = |files: &[(PathBuf, u64)]| -> Vec<String> {
+            files
+                .iter()
+                .map(|(p, _)| p.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(
+            names(&garbage.temp_files),
+            ["Mix aaa [aaa].webm", "Mix ddd [ddd].mp3.part"]
+        );
+        assert_eq!(names(&garbage.orphan_files), ["Unknown.mp3"]);
+        assert_eq!(garbage.missing.len(), 2);
+        assert_eq!(garbage.size(), 15);
+
+        collect_garbage(&config, &db, &garbage).unwrap();
+        assert!(find_garbage(&config, &db, false, &jobs).unwrap().is_empty());
+        let relinked = db.get_track(&moved.id).unwrap().unwrap();
+        assert_eq!(relinked.file_path, "Renamed [bbb].opus");
+        assert_eq!(relinked.format.as_deref(), Some("opus"));
+        // The only file left of a track isn't mistaken for a leftover
+        let relinked = db.get_track(&converted.id).unwrap().unwrap();
+        assert_eq!(relinked.file_path, "Mix ccc [ccc].webm");
+    }
+
+    #[test]
+    fn test_merge_duplicates() {
+        let (_dir, config, db) = test_library();
+
+        let now = Utc::now();
+        let mut kept = Track::new(
+            "https://www.youtube.com/watch?v=abc".to_string(),
+            "Mix".to_string(),
+            60,
+            "Mix [abc].mp3".to_string(),
+        );
+        kept.added_at = now - Duration::days(2);
+        let mut duplicate = Track::new(
+            "https://www.youtube.com/shorts/abc".to_string(),
+            "Mix (short)".to_string(),
+            60,
+            "Mix (short) [abc].mp3".to_string(),
+        );
+        duplicate.alias = Some("mix".to_string());
+        duplicate.last_played_at = Some(now);
+        // Only one track can hold the video ID
+        duplicate.video_id = None;
+        for track in [&kept, &duplicate] {
+            fs::write(config.resolve_audio_path(&track.file_path), b"audio").unwrap();
+            db.insert_track(track).unwrap();
+        }
+        let playlist = db.get_or_create_playlist("Mixes").unwrap();
+        db.add_track_to_playlist(&playlist.id, &duplicate.id)
+            .unwrap();
+
+        let found = find_duplicates(&config, &db).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].keep.id, kept.id);
+
+        merge_duplicates(&config, &db, &found).unwrap();
+        assert!(db.get_track(&duplicate.id).unwrap().is_none());
+        assert!(!config.resolve_audio_path(&duplicate.file_path).exists());
+
+        let merged = db.get_track(&kept.id).unwrap().unwrap();
+        assert_eq!(merged.alias.as_deref(), Some("mix"));
+        assert_eq!(db.get_track_by_alias("mix").unwrap().unwrap().id, kept.id);
+        assert_eq!(merged.video_id.as_deref(), Some("abc"));
+        assert!(merged.last_played_at.is_some());
+        let tracks = db.get_playlist_tracks(&playlist.id).unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].id, kept.id);
+        assert!(find_duplicates(&config, &db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_and_restore() {
+        let (_dir, config, db) = test_library();
+
+        let playlist = db.get_or_create_playlist("Mixes").unwrap();
+        let mut tracks = Vec::new();
+        for name in ["first", "second"] {
+            let mut track = Track::new(
+                format!("https://example.com/{name}"),
+                name.to_string(),
+                60,
+                format!("{name}.mp3"),
+            );
+            track.alias = Some(format!("{name}-alias"));
+            fs::write(config.resolve_audio_path(&track.file_path), b"audio").unwrap();
+            db.insert_track(&track).unwrap();
+            db.add_track_to_playlist(&playlist.id, &track.id).unwrap();
+            tracks.push(track);
+        }
+
+        db.add_alias(&tracks[0].id, "extra").unwrap();
+        let trashed = trash_track(&config, &db, &tracks[0]).unwrap();
+        assert!(db.get_track(&tracks[0].id).unwrap().is_none());
+        assert!(db.get_track_by_alias("extra").unwrap().is_none());
+        assert!(!config.resolve_audio_path("first.mp3").exists());
+        assert_eq!(trash_size(&config, &db.get_trash().unwrap()), 5);
+
+        restore_trashed(&config, &db, &trashed).unwrap();
+        assert!(config.resolve_audio_path("first.mp3").exists());
+        assert!(db.get_trash().unwrap().is_empty());
+        let restored = db.get_playlist_tracks(&playlist.id).unwrap();
+        assert_eq!(restored[0].id, tracks[0].id);
+        assert_eq!(restored[0].alias.as_deref(), Some("first-alias"));
+        assert_eq!(
+            db.get_track_aliases(&tracks[0].id).unwrap(),
+            ["first-alias", "extra"]
+        );
+        assert_eq!(restored[1].id, tracks[1].id);
+
+        // Only tracks removed before the cutoff expire
+        trash_track(&config, &db, &tracks[1]).unwrap();
+        let cutoff = Utc::now() - Duration::days(1);
+        assert!(empty_trash(&config, &db, Some(cutoff)).unwrap().is_empty());
+        assert_eq!(empty_trash(&config, &db, None).unwrap().len(), 1);
+        assert_eq!(fs::read_dir(config.trash_dir()).unwrap().count(), 0);
+    }
+}
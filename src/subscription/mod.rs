@@ -0,0 +1,228 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+
+use crate::db::Database;
+use crate::download::{DownloadPhase, Downloader, PlaylistEntry};
+use crate::models::{Subscription, Track};
+
+pub enum SyncEvent<'a> {
+    Downloading(&'a PlaylistEntry),
+    Progress(DownloadPhase),
+    Added(&'a Track),
+    Failed(&'a PlaylistEntry, &'a anyhow::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub added: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Download every entry of a subscription that no previous sync has handled,
+/// adding the new tracks to the subscription's target playlist.
+pub fn sync_subscription(
+    downloader: &Downloader,
+    db: &Database,
+    subscription: &Subscription,
+    on_event: impl Fn(SyncEvent),
+) -> Result<SyncReport> {
+    let filter = subscription
+        .filter
+        .as_deref()
+        .map(|f| f.parse::<SyncFilter>().map_err(|e| anyhow!(e)))
+        .transpose()?;
+
+    let info = downloader.get_playlist_info(&subscription.url)?;
+    let seen = db.get_subscription_entries(&subscription.id)?;
+    let mut report = SyncReport::default();
+
+    for entry in info.entries.iter().filter(|e| !seen.contains(&e.id)) {
+        if let Some(ref filter) = filter
+            && !filter.matches(entry)
+        {
+            db.add_subscription_entry(&subscription.id, &entry.id)?;
+            continue;
+        }
+
+        let track = match db.get_track_by_source(&entry.url)? {
+            Some(existing) => {
+                report.skipped += 1;
+                existing
+            }
+            None => {
+                on_event(SyncEvent::Downloading(entry));
+                let result = downloader
+                    .download(&entry.url, None, |phase| {
+                        on_event(SyncEvent::Progress(phase))
+                    })
+                    .map_err(anyhow::Error::from)
+                    .and_then(|track| db.insert_track(&track).map(|_| track));
+
+                match result {
+                    Ok(track) => {
+                        on_event(SyncEvent::Added(&track));
+                        report.added += 1;
+                        track
+                    }
+                    Err(e) => {
+                        // Leave the entry unseen so the next sync retries it
+                        on_event(SyncEvent::Failed(entry, &e));
+                        report.failed += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        if let Some(playlist_id) = subscription.playlist_id {
+            db.add_track_to_playlist(&playlist_id, &track.id)?;
+        }
+        db.add_subscription_entry(&subscription.id, &entry.id)?;
+    }
+
+    db.update_subscription_synced(&subscription.id, Utc::now())?;
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FilterTerm {
+    Duration(Comparison, u64),
+    Title(String),
+}
+
+/// Entry filter for subscriptions, e.g. `dur:>20m title:live`.
+///
+/// Every term must match. Entries whose duration is unknown pass duration terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncFilter {
+    terms: Vec<FilterTerm>,
+}
+
+impl SyncFilter {
+    pub fn matches(&self, entry: &PlaylistEntry) -> bool {
+        self.terms.iter().all(|term| match term {
+            FilterTerm::Duration(cmp, seconds) => match entry.duration {
+                Some(duration) => match cmp {
+                    Comparison::Less => duration < *seconds,
+                    Comparison::LessOrEqual => duration <= *seconds,
+                    Comparison::Equal => duration == *seconds,
+                    Comparison::GreaterOrEqual => duration >= *seconds,
+                    Comparison::Greater => duration > *seconds,
+                },
+                None => true,
+            },
+            FilterTerm::Title(text) => entry.title.to_lowercase().contains(text),
+        })
+    }
+}
+
+impl std::str::FromStr for SyncFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = s
+            .split_whitespace()
+            .map(|term| {
+                let (key, value) = term
+                    .split_once(':')
+                    .ok_or_else(|| format!("Invalid filter term: {term}"))?;
+
+                match key {
+                    "dur" | "duration" => {
+                        let (cmp, rest) = parse_comparison(value);
+                        let seconds = parse_filter_duration(rest)
+                            .ok_or_else(|| format!("Invalid duration in filter: {value}"))?;
+                        Ok(FilterTerm::Duration(cmp, seconds))
+                    }
+                    "title" if !value.is_empty() => Ok(FilterTerm::Title(value.to_lowercase())),
+                    _ => Err(format!("Invalid filter term: {term}")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if terms.is_empty() {
+            return Err("Filter is empty".to_string());
+        }
+
+        Ok(Self { terms })
+    }
+}
+
+fn parse_comparison(s: &str) -> (Comparison, &str) {
+    for (prefix, cmp) in [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = s.strip_prefix(prefix) {
+            return (cmp, rest);
+        }
+    }
+    (Comparison::Equal, s)
+}
+
+/// Parse durations like `90`, `90s`, `20m` or `1h`.
+fn parse_filter_duration(s: &str) -> Option<u64> {
+    let (number, multiplier) = match s.char_indices().last()? {
+        (i, 's') => (&s[..i], 1),
+        (i, 'm') => (&s[..i], 60),
+        (i, 'h') => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, duration: Option<u64>) -> PlaylistEntry {
+        PlaylistEntry {
+            id: "id".to_string(),
+            title: title.to_string(),
+            url: "https://www.youtube.com/watch?v=id".to_string(),
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let filter: SyncFilter = "dur:>20m title:Live".parse().unwrap();
+        assert_eq!(
+            filter.terms,
+            vec![
+                FilterTerm::Duration(Comparison::Greater, 1200),
+                FilterTerm::Title("live".to_string()),
+            ]
+        );
+
+        assert!("dur:>twenty".parse::<SyncFilter>().is_err());
+        assert!("length:20m".parse::<SyncFilter>().is_err());
+        assert!("dur:>99999999999999999h".parse::<SyncFilter>().is_err());
+        assert!("".parse::<SyncFilter>().is_err());
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let filter: SyncFilter = "dur:>=1h".parse().unwrap();
+        assert!(filter.matches(&entry("Long Mix", Some(3600))));
+        assert!(!filter.matches(&entry("Short", Some(240))));
+        assert!(filter.matches(&entry("Unknown", None)));
+
+        let filter: SyncFilter = "dur:<10m title:mix".parse().unwrap();
+        assert!(filter.matches(&entry("Quick MIX", Some(300))));
+        assert!(!filter.matches(&entry("Quick song", Some(300))));
+    }
+}
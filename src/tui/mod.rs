@@ -13,19 +13,25 @@ use ratatui::{
     widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph},
 };
 use std::io;
-use std::sync::mpsc;
 use std::time::Duration;
+use uuid::Uuid;
 
 use crate::config::Config;
 use crate::db::Database;
-use crate::download::{DownloadPhase, Downloader};
-use crate::ipc::DaemonClient;
-use crate::models::{PlaybackState, Track};
-
-enum DownloadUpdate {
-    Status(String),
-    Progress(DownloadPhase),
-    Done(Result<Track, String>),
+use crate::download::resolve_source;
+use crate::ipc::{DaemonClient, DaemonResponse};
+use crate::models::{
+    Bookmark, DownloadJob, FileStatus, JobStatus, LoopRegion, PlaybackState, SourceStatus, Track,
+    TrashedTrack,
+};
+use crate::storage;
+
+/// A destructive action that `u` can take back.
+enum Undo {
+    /// A track was moved to the trash
+    Remove(Box<TrashedTrack>),
+    /// A track's alias was changed from this
+    Rename { id: Uuid, alias: Option<String> },
 }
 
 pub struct Tui {
@@ -35,6 +41,10 @@ pub struct Tui {
     tracks: Vec<Track>,
     library_state: ListState,
     playback_state: PlaybackState,
+    /// Bookmarks of the playing track, read from the library when it starts
+    marks: Vec<Bookmark>,
+    /// Start of an A-B loop waiting for its end
+    loop_start: Option<Duration>,
     search_query: String,
     search_mode: bool,
     edit_mode: bool,
@@ -42,7 +52,8 @@ pub struct Tui {
     add_mode: bool,
     add_url: String,
     status_message: Option<String>,
-    download_rx: Option<mpsc::Receiver<DownloadUpdate>>,
+    downloads: Vec<DownloadJob>,
+    undo: Option<Undo>,
 }
 
 impl Tui {
@@ -61,6 +72,9 @@ impl Tui {
             library_state.select(Some(0));
         }
 
+        let downloads = client.list_downloads().unwrap_or_default();
+        let marks = Self::load_marks(&db, &playback_state);
+
         Ok(Self {
             config,
             db,
@@ -68,6 +82,8 @@ impl Tui {
             tracks,
             library_state,
             playback_state,
+            marks,
+            loop_start: None,
             search_query: String::new(),
             search_mode: false,
             edit_mode: false,
@@ -75,7 +91,8 @@ impl Tui {
             add_mode: false,
             add_url: String::new(),
             status_message: None,
-            download_rx: None,
+            downloads,
+            undo: None,
         })
     }
 
@@ -105,52 +122,13 @@ impl Tui {
             // Refresh playback state
             if self.client.is_daemon_running() {
                 if let Ok(state) = self.client.get_status() {
-                    self.playback_state = state;
+                    self.set_playback_state(state);
                 }
             }
 
-            // Poll for download progress updates
-            if let Some(rx) = &self.download_rx {
-                match rx.try_recv() {
-                    Ok(DownloadUpdate::Status(msg)) => {
-                        self.status_message = Some(msg);
-                    }
-                    Ok(DownloadUpdate::Progress(phase)) => match phase {
-                        DownloadPhase::Downloading { percent, speed, eta } => {
-                            self.status_message =
-                                Some(format!("Downloading: {:.1}% ({}, ETA {})", percent, speed, eta));
-                        }
-                        DownloadPhase::Converting => {
-                            self.status_message = Some("Converting audio...".to_string());
-                        }
-                    },
-                    Ok(DownloadUpdate::Done(result)) => {
-                        match result {
-                            Ok(track) => {
-                                if self.db.insert_track(&track).is_ok() {
-                                    self.status_message =
-                                        Some(format!("Added: {}", track.display_name()));
-                                    if let Ok(tracks) = self.db.get_all_tracks() {
-                                        self.tracks = tracks;
-                                        self.library_state.select(Some(0));
-                                    }
-                                } else {
-                                    self.status_message =
-                                        Some("Failed to save track".to_string());
-                                }
-                            }
-                            Err(e) => {
-                                self.status_message = Some(format!("Download failed: {}", e));
-                            }
-                        }
-                        self.download_rx = None;
-                    }
-                    Err(mpsc::TryRecvError::Empty) => {}
-                    Err(mpsc::TryRecvError::Disconnected) => {
-                        self.status_message = Some("Download thread terminated unexpectedly".to_string());
-                        self.download_rx = None;
-                    }
-                }
+            // Poll the daemon's download queue while downloads are in flight
+            if self.downloads.iter().any(|j| !j.status.is_finished()) {
+                self.refresh_downloads();
             }
 
             terminal.draw(|f| self.ui(f))?;
@@ -223,13 +201,28 @@ impl Tui {
                                 self.search_mode = true;
                             }
                             KeyCode::Char('e') => self.start_edit(),
-                            KeyCode::Char('a') if self.download_rx.is_none() => {
+                            KeyCode::Char('a') => {
                                 self.add_mode = true;
                             }
+                            KeyCode::Char('r') => self.retry_download(),
+                            KeyCode::Char('d') => self.remove_selected(),
+                            KeyCode::Char('u') => self.undo(),
                             KeyCode::Up | KeyCode::Char('k') => self.select_prev(),
                             KeyCode::Down | KeyCode::Char('j') => self.select_next(),
                             KeyCode::Left | KeyCode::Char('h') => self.seek_backward(),
                             KeyCode::Right | KeyCode::Char('l') => self.seek_forward(),
+                            KeyCode::Char(']') => {
+                                let _ = self.client.next_chapter();
+                            }
+                            KeyCode::Char('[') => {
+                                let _ = self.client.previous_chapter();
+                            }
+                            KeyCode::Char(c @ '1'..='9') => {
+                                self.goto_mark(c as usize - '1' as usize)
+                            }
+                            KeyCode::Char('A') => self.set_loop_start(),
+                            KeyCode::Char('B') => self.set_loop_end(),
+                            KeyCode::Char('L') => self.clear_loop(),
                             KeyCode::Enter => self.play_selected(),
                             KeyCode::Char(' ') => self.toggle_or_play(),
                             KeyCode::Char('+') | KeyCode::Char('=') => self.volume_up(),
@@ -246,7 +239,7 @@ impl Tui {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(7), // Now playing (larger)
+                Constraint::Length(9), // Now playing (larger)
                 Constraint::Min(8),    // Main content
                 Constraint::Length(1), // Help
             ])
@@ -277,8 +270,9 @@ impl Tui {
                 .margin(1)
                 .constraints([
                     Constraint::Length(1), // Track title
-                    Constraint::Length(1), // Spacer
+                    Constraint::Length(1), // Chapter
                     Constraint::Length(1), // Progress bar
+                    Constraint::Length(1), // Marks
                     Constraint::Length(1), // Time + controls
                 ])
                 .split(inner);
@@ -299,9 +293,27 @@ impl Tui {
             .alignment(Alignment::Center);
             f.render_widget(title, chunks[0]);
 
+            if let Some(chapter) = self.playback_state.current_chapter() {
+                let index = self.playback_state.chapter.unwrap_or_default();
+                let chapter = Paragraph::new(format!(
+                    "{}/{}  {}",
+                    index + 1,
+                    track.chapters.len(),
+                    chapter.title
+                ))
+                .style(Style::default().fg(Color::Gray))
+                .alignment(Alignment::Center);
+                f.render_widget(chapter, chunks[1]);
+            }
+
             // Progress bar
-            let progress = if track.duration > 0 {
-                (self.playback_state.position as f64 / track.duration as f64).min(1.0)
+            // Progress through the trimmed window, if there is one
+            let elapsed = self
+                .playback_state
+                .position
+                .saturating_sub(track.play_start());
+            let progress = if track.play_duration() > 0 {
+                (elapsed as f64 / track.play_duration() as f64).min(1.0)
             } else {
                 0.0
             };
@@ -312,9 +324,47 @@ impl Tui {
                 .label("");
             f.render_widget(gauge, chunks[2]);
 
+            // Numbered marks and the A-B loop under their place on the
+            // progress bar
+            let width = chunks[3].width as usize;
+            if width > 0 && track.play_duration() > 0 {
+                let column = |position: u64| {
+                    let offset = position.checked_sub(track.play_start())?;
+                    (offset <= track.play_duration())
+                        .then(|| offset as usize * (width - 1) / track.play_duration() as usize)
+                };
+                let loop_style = Style::default().fg(Color::Cyan);
+                let mut row = vec![(' ', Style::default()); width];
+                if let Some(region) = self.playback_state.loop_region {
+                    let start = column(region.start.as_secs()).unwrap_or(0);
+                    let end = column(region.end.as_secs()).unwrap_or(width - 1);
+                    for cell in &mut row[start..=end.max(start)] {
+                        *cell = ('=', loop_style);
+                    }
+                    row[start].0 = 'A';
+                    row[end.max(start)].0 = 'B';
+                } else if let Some(start) = self.loop_start.and_then(|s| column(s.as_secs())) {
+                    row[start] = ('A', loop_style);
+                }
+                for (i, mark) in self.marks.iter().take(9).enumerate() {
+                    if let Some(column) = column(mark.position) {
+                        row[column] = (
+                            char::from_digit(i as u32 + 1, 10).unwrap_or('?'),
+                            Style::default().fg(Color::Yellow),
+                        );
+                    }
+                }
+                let marks = Paragraph::new(Line::from(
+                    row.into_iter()
+                        .map(|(c, style)| Span::styled(c.to_string(), style))
+                        .collect::<Vec<_>>(),
+                ));
+                f.render_widget(marks, chunks[3]);
+            }
+
             // Time display and controls
             let current_time = Self::format_time(self.playback_state.position);
-            let total_time = Self::format_time(track.duration);
+            let total_time = Self::format_time(track.play_end());
 
             let time_line = Line::from(vec![
                 Span::raw(format!("{}  ", current_time)),
@@ -331,7 +381,7 @@ impl Tui {
             ]);
 
             let time_para = Paragraph::new(time_line).alignment(Alignment::Center);
-            f.render_widget(time_para, chunks[3]);
+            f.render_widget(time_para, chunks[4]);
         } else {
             // Nothing playing
             let chunks = Layout::default()
@@ -365,7 +415,7 @@ impl Tui {
                     .map(|ct| ct.id == t.id)
                     .unwrap_or(false);
 
-                let style = if !t.available {
+                let style = if !t.is_playable() {
                     Style::default().fg(Color::DarkGray)
                 } else if is_current {
                     Style::default().fg(Color::Cyan)
@@ -374,11 +424,25 @@ impl Tui {
                 };
 
                 let prefix = if is_current { "♪ " } else { "  " };
+                let file_status = if t.remote_only {
+                    " [remote]"
+                } else if t.file_status == FileStatus::Missing {
+                    " [missing]"
+                } else {
+                    ""
+                };
+                let source_status = if t.source_status == SourceStatus::Unavailable {
+                    " [gone]"
+                } else {
+                    ""
+                };
                 ListItem::new(format!(
-                    "{}{} - {}",
+                    "{}{} - {}{}{}",
                     prefix,
                     t.display_name(),
-                    t.format_duration()
+                    t.format_duration(),
+                    file_status,
+                    source_status
                 ))
                 .style(style)
             })
@@ -418,7 +482,7 @@ impl Tui {
             (format!(" {}", msg), Style::default().fg(Color::Yellow))
         } else {
             (
-                " q:Quit  /:Search  a:Add  e:Edit  ↑↓:Nav  ←→:Seek  Space:Play  +/-:Vol"
+                " q:Quit  /:Search  a:Add  r:Retry  e:Edit  d:Remove  u:Undo  ↑↓:Nav  ←→:Seek  []:Chapter  1-9:Mark  A/B/L:Loop  Space:Play  +/-:Vol"
                     .to_string(),
                 Style::default().fg(Color::DarkGray),
             )
@@ -467,7 +531,7 @@ impl Tui {
         let Some(track) = self.tracks.get(i) else {
             return;
         };
-        if track.available {
+        if track.is_playable() {
             let _ = self.client.play(track.clone());
         }
     }
@@ -510,6 +574,94 @@ impl Tui {
         }
     }
 
+    /// Seek to the playing track's `index`th mark.
+    fn goto_mark(&mut self, index: usize) {
+        let Some(mark) = self.marks.get(index) else {
+            return;
+        };
+        if self.client.seek(mark.position).is_ok() {
+            self.status_message = Some(format!(
+                "Mark {}: {} ({})",
+                index + 1,
+                mark.name,
+                Self::format_time(mark.position)
+            ));
+        }
+    }
+
+    /// The playing position as of now rather than the last refresh.
+    fn precise_position(&mut self) -> Duration {
+        if let Ok(state) = self.client.get_status() {
+            self.set_playback_state(state);
+        }
+        self.playback_state.precise_position
+    }
+
+    /// Take the daemon's latest state. When another track starts, a loop
+    /// start set on the last one is dropped and the new one's marks read.
+    fn set_playback_state(&mut self, state: PlaybackState) {
+        let playing = |s: &PlaybackState| s.current_track.as_ref().map(|t| t.id);
+        let changed = playing(&state) != playing(&self.playback_state);
+        self.playback_state = state;
+        if changed {
+            self.loop_start = None;
+            self.marks = Self::load_marks(&self.db, &self.playback_state);
+        }
+    }
+
+    /// Bookmarks of the playing track, from the library rather than the
+    /// daemon's copy, which can predate marks added since it was queued.
+    fn load_marks(db: &Database, state: &PlaybackState) -> Vec<Bookmark> {
+        state
+            .current_track
+            .as_ref()
+            .and_then(|t| db.get_track(&t.id).ok().flatten())
+            .map(|t| t.bookmarks)
+            .unwrap_or_default()
+    }
+
+    /// Remember the current position as the start of a loop.
+    fn set_loop_start(&mut self) {
+        let position = self.precise_position();
+        if self.playback_state.current_track.is_some() {
+            self.loop_start = Some(position);
+            self.status_message = Some(format!(
+                "Loop from {}, press B at its end",
+                Self::format_time(position.as_secs())
+            ));
+        }
+    }
+
+    /// Loop from the start set with A to the current position.
+    fn set_loop_end(&mut self) {
+        // Read first, so an A from a track that has since ended is dropped
+        let end = self.precise_position();
+        let Some(start) = self.loop_start else {
+            self.status_message = Some("Press A at the start of the loop first".to_string());
+            return;
+        };
+        let region = LoopRegion { start, end };
+        self.status_message = Some(match self.client.set_loop(Some(region)) {
+            Ok(DaemonResponse::Error(e)) => e,
+            Ok(_) => {
+                self.loop_start = None;
+                format!(
+                    "Looping {} - {}",
+                    Self::format_time(start.as_secs()),
+                    Self::format_time(region.end.as_secs())
+                )
+            }
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn clear_loop(&mut self) {
+        self.loop_start = None;
+        if self.playback_state.loop_region.is_some() && self.client.set_loop(None).is_ok() {
+            self.status_message = Some("Loop off".to_string());
+        }
+    }
+
     fn start_edit(&mut self) {
         let Some(i) = self.library_state.selected() else {
             return;
@@ -518,7 +670,7 @@ impl Tui {
             return;
         };
         // Pre-fill with current alias or title
-        self.edit_text = track.alias.clone().unwrap_or_else(|| track.title.clone());
+        self.edit_text = track.display_name().to_string();
         self.edit_mode = true;
     }
 
@@ -538,22 +690,83 @@ impl Tui {
 
         // Save the new alias to the database
         let new_alias = self.edit_text.trim().to_string();
-        let alias = if new_alias == track.title {
+        let alias = if new_alias == track.display_title() {
             None // Clear alias if it matches the title
         } else {
             Some(new_alias.as_str())
         };
 
         if self.db.update_track_alias(&track.id, alias).is_ok() {
-            // Update local track list
-            if let Some(t) = self.tracks.get_mut(i) {
-                t.alias = alias.map(|s| s.to_string());
+            let id = track.id;
+            self.undo = Some(Undo::Rename {
+                id,
+                alias: track.alias.clone(),
+            });
+            // Update local track list; clearing an alias can show another
+            if let Ok(Some(renamed)) = self.db.get_track(&id) {
+                self.tracks[i].alias = renamed.alias;
             }
         }
 
         self.edit_text.clear();
     }
 
+    fn remove_selected(&mut self) {
+        let Some(i) = self.library_state.selected() else {
+            return;
+        };
+        let Some(track) = self.tracks.get(i) else {
+            return;
+        };
+
+        match storage::trash_track(&self.config, &self.db, track) {
+            Ok(trashed) => {
+                self.status_message = Some(format!(
+                    "Moved to trash: {} (u to undo)",
+                    track.display_name()
+                ));
+                self.undo = Some(Undo::Remove(Box::new(trashed)));
+                self.tracks.remove(i);
+                if i >= self.tracks.len() {
+                    self.library_state.select(self.tracks.len().checked_sub(1));
+                }
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to remove: {e}"));
+            }
+        }
+    }
+
+    /// Take back the last removal or rename.
+    fn undo(&mut self) {
+        let Some(undo) = self.undo.take() else {
+            self.status_message = Some("Nothing to undo".to_string());
+            return;
+        };
+
+        let result = match &undo {
+            Undo::Remove(trashed) => storage::restore_trashed(&self.config, &self.db, trashed)
+                .map(|_| format!("Restored: {}", trashed.track.display_name())),
+            Undo::Rename { id, alias } => self
+                .db
+                .update_track_alias(id, alias.as_deref())
+                .map(|_| "Rename undone".to_string()),
+        };
+
+        match result {
+            Ok(message) => {
+                self.status_message = Some(message);
+                if let Ok(tracks) = self.db.get_all_tracks() {
+                    self.tracks = tracks;
+                }
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Undo failed: {e}"));
+                self.undo = Some(undo);
+            }
+        }
+    }
+
     fn add_track(&mut self) {
         let url = self.add_url.trim().to_string();
         self.add_url.clear();
@@ -562,56 +775,100 @@ impl Tui {
             return;
         }
 
-        // Check if it looks like a YouTube URL
-        if !url.contains("youtube.com") && !url.contains("youtu.be") {
-            self.status_message = Some("Invalid URL - must be a YouTube URL".to_string());
+        // The daemon picks an extractor; just reject things that are neither
+        // URLs nor existing files
+        let url = resolve_source(&url);
+        if !url.contains("://") {
+            self.status_message = Some("Invalid URL - must be a URL or audio file".to_string());
             return;
         }
 
-        let (tx, rx) = mpsc::channel();
-        self.download_rx = Some(rx);
-        self.status_message = Some("Checking video info...".to_string());
-
-        let config = self.config.clone();
-        let db_path = config.db_path();
-
-        std::thread::spawn(move || {
-            let downloader = Downloader::new(config);
-
-            // Get video info and check for duplicates
-            let canonical_url = match downloader.get_video_info(&url) {
-                Ok((title, canonical_url, _)) => {
-                    // Open a separate DB connection for the duplicate check
-                    if let Ok(db) = Database::open(&db_path)
-                        && let Ok(Some(_)) = db.get_track_by_url(&canonical_url)
-                    {
-                        let _ = tx.send(DownloadUpdate::Done(Err(format!(
-                            "Already in library: {}",
-                            title
-                        ))));
-                        return;
-                    }
-                    let _ = tx.send(DownloadUpdate::Status(format!("Downloading: {}...", title)));
-                    canonical_url
-                }
-                Err(e) => {
-                    let _ = tx.send(DownloadUpdate::Done(Err(format!("{}", e))));
-                    return;
+        match self.client.enqueue_download(url, None, None) {
+            Ok(job) => {
+                self.status_message = Some(format!("Queued: {}", job.url));
+                self.downloads.push(job);
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to queue download: {e}"));
+            }
+        }
+    }
+
+    /// Re-queue the most recent download that failed for a transient reason.
+    fn retry_download(&mut self) {
+        let Some(job) = self.downloads.iter().rev().find(|j| {
+            j.status == JobStatus::Failed && j.error.as_ref().is_some_and(|e| e.is_transient())
+        }) else {
+            self.status_message = Some("No failed downloads to retry".to_string());
+            return;
+        };
+
+        let (id, name) = (job.id, job.display_name().to_string());
+        match self.client.retry_download(&id.to_string()) {
+            Ok(_) => {
+                self.status_message = Some(format!("Retrying: {name}"));
+                // Mark it active so the queue gets polled again
+                if let Some(job) = self.downloads.iter_mut().find(|j| j.id == id) {
+                    job.status = JobStatus::Queued;
+                    job.error = None;
                 }
-            };
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to retry download: {e}"));
+            }
+        }
+    }
 
-            let tx_progress = tx.clone();
-            match downloader.download(&canonical_url, move |phase| {
-                let _ = tx_progress.send(DownloadUpdate::Progress(phase));
-            }) {
-                Ok(track) => {
-                    let _ = tx.send(DownloadUpdate::Done(Ok(track)));
+    fn refresh_downloads(&mut self) {
+        let Ok(jobs) = self.client.list_downloads() else {
+            return;
+        };
+
+        let mut library_changed = false;
+        for job in &jobs {
+            let was_active = self
+                .downloads
+                .iter()
+                .any(|j| j.id == job.id && !j.status.is_finished());
+            if !was_active {
+                continue;
+            }
+
+            match job.status {
+                JobStatus::Done => {
+                    self.status_message = Some(format!("Added: {}", job.display_name()));
+                    library_changed = true;
+                }
+                JobStatus::Failed => {
+                    self.status_message = Some(match &job.error {
+                        Some(e) if e.is_transient() => {
+                            format!("Download failed: {e} (press r to retry)")
+                        }
+                        Some(e) if e.is_unavailable() => {
+                            format!("Can't download {}: {e}", job.display_name())
+                        }
+                        Some(e) => format!("Download failed: {e}"),
+                        None => "Download failed".to_string(),
+                    });
                 }
-                Err(e) => {
-                    let _ = tx.send(DownloadUpdate::Done(Err(format!("{}", e))));
+                JobStatus::Running => {
+                    let pending = jobs.iter().filter(|j| !j.status.is_finished()).count();
+                    let progress = job.progress.as_deref().unwrap_or("Starting...");
+                    self.status_message = Some(format!(
+                        "Downloading ({pending} pending): {} {progress}",
+                        job.display_name()
+                    ));
                 }
+                JobStatus::Queued | JobStatus::Cancelled => {}
             }
-        });
+        }
+
+        if library_changed && let Ok(tracks) = self.db.get_all_tracks() {
+            self.tracks = tracks;
+            self.library_state.select(Some(0));
+        }
+
+        self.downloads = jobs;
     }
 
     fn apply_search(&mut self) {
@@ -630,7 +887,9 @@ impl Tui {
             .iter()
             .enumerate()
             .filter_map(|(i, track)| {
-                let title_score = matcher.fuzzy_match(&track.title, query).unwrap_or(0);
+                let title_score = matcher
+                    .fuzzy_match(track.display_title(), query)
+                    .unwrap_or(0);
                 let alias_score = track
                     .alias
                     .as_ref()
@@ -641,7 +900,7 @@ impl Tui {
             })
             .collect();
 
-        matches.sort_by(|a, b| b.1.cmp(&a.1));
+        matches.sort_by_key(|m| std::cmp::Reverse(m.1));
 
         if let Some((index, _)) = matches.first() {
             self.library_state.select(Some(*index));
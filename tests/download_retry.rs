@@ -0,0 +1,138 @@
+//! Exercises download retries against a fake `yt-dlp` placed first on `PATH`.
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Fake yt-dlp: metadata requests succeed, downloads fail with `$FAIL_MESSAGE`
+/// until `$FAIL_TIMES` attempts have been made. Each attempt is logged to
+/// `$ATTEMPTS_FILE`.
+const FAKE_YT_DLP: &str = r#"#!/bin/sh
+case " $* " in
+  *" --version "*) echo 2025.01.01; exit 0 ;;
+  *" --dump-json "*)
+    echo '{"id":"abc123","title":"Fake Mix","duration":60,"webpage_url":"https://www.youtube.com/watch?v=abc123"}'
+    exit 0 ;;
+esac
+
+echo attempt >> "$ATTEMPTS_FILE"
+if [ "$(wc -l < "$ATTEMPTS_FILE")" -le "$FAIL_TIMES" ]; then
+  echo "$FAIL_MESSAGE" >&2
+  exit 1
+fi
+
+prev=""
+for arg in "$@"; do
+  if [ "$prev" = "-o" ]; then out="$arg"; fi
+  prev="$arg"
+done
+file=$(echo "$out" | sed 's/%(ext)s/mp3/')
+echo audio > "$file"
+echo "$file"
+"#;
+
+struct Sandbox {
+    dir: TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+
+        let bin = dir.path().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        write_script(&bin.join("yt-dlp"), FAKE_YT_DLP);
+        write_script(&bin.join("ffmpeg"), "#!/bin/sh\necho ffmpeg version 6.0\n");
+
+        let config = format!(
+            "[storage]\npath = {:?}\n\n[download.retries]\nattempts = 3\nbackoff = 0\n",
+            dir.path().join("library")
+        );
+        for config_dir in [
+            dir.path().join("config/mixyt"),
+            dir.path().join("home/Library/Application Support/mixyt"),
+        ] {
+            fs::create_dir_all(&config_dir).unwrap();
+            fs::write(config_dir.join("config.toml"), &config).unwrap();
+        }
+
+        Self { dir }
+    }
+
+    fn attempts_file(&self) -> PathBuf {
+        self.dir.path().join("attempts")
+    }
+
+    fn attempts(&self) -> usize {
+        fs::read_to_string(self.attempts_file())
+            .map(|s| s.lines().count())
+            .unwrap_or(0)
+    }
+
+    fn add(&self, fail_times: u32, fail_message: &str) -> assert_cmd::assert::Assert {
+        let path = format!(
+            "{}:{}",
+            self.dir.path().join("bin").display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+
+        cargo_bin_cmd!("mixyt")
+            .args(["add", "https://www.youtube.com/watch?v=abc123"])
+            .env("PATH", path)
+            .env("HOME", self.dir.path().join("home"))
+            .env("XDG_CONFIG_HOME", self.dir.path().join("config"))
+            .env("ATTEMPTS_FILE", self.attempts_file())
+            .env("FAIL_TIMES", fail_times.to_string())
+            .env("FAIL_MESSAGE", fail_message)
+            .assert()
+    }
+}
+
+fn write_script(path: &Path, contents: &str) {
+    fs::write(path, contents).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn retries_network_failures_until_success() {
+    let sandbox = Sandbox::new();
+
+    sandbox
+        .add(
+            2,
+            "ERROR: Unable to download video data: Connection reset by peer",
+        )
+        .success()
+        .stdout(predicate::str::contains("Added: Fake Mix"))
+        .stderr(predicate::str::contains("Attempt 2/3 failed"));
+
+    assert_eq!(sandbox.attempts(), 3);
+}
+
+#[test]
+fn gives_up_after_configured_attempts() {
+    let sandbox = Sandbox::new();
+
+    sandbox
+        .add(5, "ERROR: Unable to download video data: HTTP Error 503")
+        .failure()
+        .stderr(predicate::str::contains("HTTP Error 503"));
+
+    assert_eq!(sandbox.attempts(), 3);
+}
+
+#[test]
+fn does_not_retry_permanent_failures() {
+    let sandbox = Sandbox::new();
+
+    sandbox
+        .add(5, "ERROR: [youtube] abc123: Private video")
+        .failure()
+        .stderr(predicate::str::contains("Video is private"));
+
+    assert_eq!(sandbox.attempts(), 1);
+}